@@ -0,0 +1,119 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, Type, Visibility};
+
+use crate::attrs::ActixAttrs;
+use crate::{type_key, variant_with_fields};
+
+/// Generate `actix::Message` impls for the enum and each payload type, sharing one result type,
+/// and a `dispatch()` method forwarding the held payload to the actor's `Handler` for its type
+///
+/// Every variant, skipped ones included, must hold a single field, since only those payloads
+/// can be handled as messages of their own. Payloads that are certainly foreign types are
+/// rejected, since the orphan rule forbids their `Message` impl.
+pub fn actix_impls(
+    name: &Ident,
+    krate: &TokenStream,
+    vis: &Visibility,
+    variants: &[&syn::Variant],
+    attrs: &ActixAttrs,
+) -> syn::Result<TokenStream> {
+    let result = match &attrs.result {
+        Some(result) => quote!(#result),
+        None => quote!(()),
+    };
+
+    let mut message_impls = Vec::new();
+    let mut handler_bounds = Vec::new();
+    let mut seen_types = Vec::new();
+    let mut arms = Vec::new();
+    for variant in variants {
+        let field_type = match variant.fields.iter().collect::<Vec<_>>().as_slice() {
+            [field] => &field.ty,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "actix dispatch needs every variant to hold a single field",
+                ));
+            }
+        };
+        if is_foreign(field_type) {
+            return Err(syn::Error::new_spanned(
+                field_type,
+                "actix dispatch needs payload types defined in this crate, since `Message` \
+                can't be implemented for foreign types; wrap it in a local type",
+            ));
+        }
+        let pattern = variant_with_fields(&quote!(#name), variant, &[quote!(message)]);
+        arms.push(quote! {
+            #pattern => {
+                <A as #krate::__private::actix::Handler<#field_type>>::handle(actor, message, ctx)
+            }
+        });
+
+        let key = type_key(&variant.fields);
+        if seen_types.contains(&key) {
+            continue;
+        }
+        seen_types.push(key);
+        message_impls.push(quote! {
+            impl #krate::__private::actix::Message for #field_type {
+                type Result = #result;
+            }
+        });
+        handler_bounds.push(quote!(#krate::__private::actix::Handler<#field_type, Result = H>));
+    }
+
+    Ok(quote! {
+        impl #krate::__private::actix::Message for #name {
+            type Result = #result;
+        }
+
+        #(#message_impls)*
+
+        impl #name {
+            /// Handle the held payload with `actor`'s `Handler` for its type
+            ///
+            /// Call this from the actor's `Handler` impl for the enum. Every payload handler must
+            /// share the `Result` type `H`.
+            #vis fn dispatch<A, H>(self, actor: &mut A, ctx: &mut A::Context) -> H
+            where
+                A: #krate::__private::actix::Actor #(+ #handler_bounds)*,
+            {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
+
+/// Whether `ty` is certainly not defined in the deriving crate: a primitive, tuple, array or slice,
+/// or a std type
+///
+/// References and boxes of local types are local as far as the orphan rule is concerned.
+fn is_foreign(ty: &Type) -> bool {
+    const PRELUDE: &[&str] = &[
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64", "String", "Vec", "Option", "Result",
+    ];
+    match ty {
+        Type::Tuple(_) | Type::Array(_) | Type::Slice(_) | Type::Ptr(_) | Type::BareFn(_) => true,
+        Type::Paren(paren) => is_foreign(&paren.elem),
+        Type::Group(group) => is_foreign(&group.elem),
+        Type::Path(path) if path.qself.is_none() => {
+            let segments = &path.path.segments;
+            match segments.first() {
+                Some(first) if segments.len() > 1 => {
+                    ["std", "core", "alloc"]
+                        .iter()
+                        .any(|krate| first.ident == krate)
+                        && segments.last().is_some_and(|last| last.ident != "Box")
+                }
+                Some(only) => PRELUDE.iter().any(|name| only.ident == name),
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
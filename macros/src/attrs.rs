@@ -0,0 +1,677 @@
+use proc_macro2::Span;
+use syn::{
+    Attribute, Ident, LitBool, LitInt, LitStr, Path, Token, Type, Variant, Visibility,
+    meta::ParseNestedMeta, parenthesized, parse::Parse, punctuated::Punctuated, spanned::Spanned,
+};
+
+/// Options set on the enum itself with `#[type_enum(...)]`
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// Emit `#[cfg(test)]` round-trip tests for every variant
+    pub test_roundtrip: bool,
+    /// Serde format module, like `serde_json`, whose `to_string` and `from_str` the round-trip
+    /// tests also go through
+    pub roundtrip_format: Option<Path>,
+    /// Extra derives applied to the generated companion types
+    pub companion_derives: Vec<Path>,
+    /// Name of the generated kind enum, instead of `<Name>Kind`
+    pub kind_name: Option<Ident>,
+    /// Generate the borrowed `<Name>Ref<'a>` mirror enum
+    pub ref_enum: bool,
+    /// Name of the generated ref enum, instead of `<Name>Ref`
+    pub ref_name: Option<Ident>,
+    /// Generate the mutably borrowed `<Name>Mut<'a>` mirror enum
+    pub mut_enum: bool,
+    /// Name of the generated mut enum, instead of `<Name>Mut`
+    pub mut_name: Option<Ident>,
+    /// Generate the `<Name>Owned` enum, with the borrowed payloads replaced by owned ones
+    pub owned: bool,
+    /// Name of the generated owned enum, instead of `<Name>Owned`
+    pub owned_name: Option<Ident>,
+    /// Place the companion items into a generated module
+    pub module: Option<ModuleAttrs>,
+    /// Operator traits to implement by applying the operator to matching payloads
+    pub delegate_ops: Vec<Ident>,
+    /// Generate `into_parts()` and `from_parts()`
+    pub parts: bool,
+    /// Generate the `<Name>Partition` struct and `partition()`
+    pub partition: bool,
+    /// Generate the `<Name>Visitor` trait and `accept()`
+    pub visitor: bool,
+    /// Generate the `<Name>AsyncVisitor` trait and `accept_async()`
+    pub async_visitor: bool,
+    /// Generate the `<Name>Handler` trait and `handle()`
+    pub handler: bool,
+    /// Generate conversions to and from `bson::Bson`
+    pub bson: Option<BsonAttrs>,
+    /// Generate `From<Enum> for opentelemetry::Value`
+    pub otel: bool,
+    /// Allowed state transitions between variants, as `(from, to)` pairs
+    pub transitions: Vec<(Ident, Ident)>,
+    /// Enums holding every payload type of this one, converted to and from
+    pub subset_of: Vec<Type>,
+    /// Generate `actix::Message` impls and `dispatch()`
+    pub actix: Option<ActixAttrs>,
+    /// Largest payload size in bytes allowed without a `Box`
+    pub auto_box_over: Option<usize>,
+    /// Generate tagged `Serialize` and `Deserialize` impls
+    pub serde: Option<SerdeAttrs>,
+    /// Mark generated extraction methods `#[must_use]`, on unless set to `false`
+    pub must_use: Option<bool>,
+    /// Generate `ToRedisArgs` and `FromRedisValue`
+    pub redis: bool,
+    /// Generate a `ts_rs::TS` impl matching the serde representation
+    pub ts: bool,
+    /// Count constructions and failed extractions per variant
+    pub telemetry: bool,
+    /// Generate `Hash`, `PartialOrd` and `Ord` ordering by variant precedence, then payload
+    pub delegate_ord: bool,
+    /// Generate `PartialEq<T>` for each payload type `T`, in both directions
+    pub payload_eq: bool,
+    /// Generate `PartialEq<T>` and `PartialOrd<T>` for each payload type `T`, in both directions
+    pub payload_cmp: bool,
+    /// Generate `Display` forwarding to the held value
+    pub display: bool,
+    /// Generate `Error` forwarding to the held value, along with `Display`
+    pub error: bool,
+    /// Generate an `as_<variant>()` getter for each variant
+    pub getters: bool,
+    /// Generate an `is_<variant>()` predicate for each variant and `holds::<T>()`
+    pub is_methods: bool,
+    /// Generate a `const fn from_<primitive>()` constructor for each variant holding a primitive
+    pub const_constructors: bool,
+    /// Generate `discriminant()` returning each variant's declared discriminant
+    pub discriminant: bool,
+    /// Path to the `type_enum` crate, instead of `::type_enum`
+    pub crate_path: Option<Path>,
+    /// Trait families to implement for each payload, narrowed with `only(...)`
+    pub families: Families,
+}
+
+/// Trait families the derive implements for each payload type
+#[derive(Clone, Copy)]
+pub struct Families {
+    /// The `only` of `only(...)`, or the call site if it wasn't given
+    pub span: Span,
+    pub from: bool,
+    pub value: bool,
+    pub value_mut: bool,
+    pub into_value: bool,
+    /// `TryFrom<Enum>` and `TryFrom<&Enum>` on the payload type
+    pub try_from_enum: bool,
+}
+
+impl Default for Families {
+    fn default() -> Self {
+        Families {
+            span: Span::call_site(),
+            from: true,
+            value: true,
+            value_mut: true,
+            into_value: true,
+            try_from_enum: true,
+        }
+    }
+}
+
+impl Families {
+    /// The families left of these after a variant's `no_from` and `skip(...)`
+    pub fn restrict(&self, variant: &Families) -> Families {
+        Families {
+            span: self.span,
+            from: self.from && variant.from,
+            value: self.value && variant.value,
+            value_mut: self.value_mut && variant.value_mut,
+            into_value: self.into_value && variant.into_value,
+            try_from_enum: self.try_from_enum && variant.try_from_enum,
+        }
+    }
+
+    /// Reject the options whose generated code relies on a left-out family
+    pub fn check(&self, attrs: &ContainerAttrs) -> syn::Result<()> {
+        let needs = [
+            (
+                "test_roundtrip",
+                attrs.test_roundtrip,
+                &["from", "value", "into_value"][..],
+            ),
+            (
+                "transitions",
+                !attrs.transitions.is_empty(),
+                &["from", "into_value"][..],
+            ),
+            ("bson", attrs.bson.is_some(), &["from"][..]),
+            ("redis", attrs.redis, &["from"][..]),
+        ];
+        for (option, enabled, families) in needs {
+            let missing = families
+                .iter()
+                .find(|family| !self.contains(family))
+                .filter(|_| enabled);
+            if let Some(family) = missing {
+                return Err(syn::Error::new(
+                    self.span,
+                    format!(
+                        "`#[type_enum({})]` needs the `{}` impls, add `{}` to `only(...)`",
+                        option, family, family
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn contains(&self, family: &str) -> bool {
+        match family {
+            "from" => self.from,
+            "value" => self.value,
+            "value_mut" => self.value_mut,
+            "into_value" => self.into_value,
+            "try_from_enum" => self.try_from_enum,
+            _ => false,
+        }
+    }
+
+    /// Parse a list of family names like `(from, value)`, setting each one to `enabled`
+    fn parse_list(&mut self, meta: &ParseNestedMeta, enabled: bool) -> syn::Result<()> {
+        meta.parse_nested_meta(|meta| {
+            let family = match meta.path.get_ident() {
+                Some(ident) if ident == "from" => &mut self.from,
+                Some(ident) if ident == "value" => &mut self.value,
+                Some(ident) if ident == "value_mut" => &mut self.value_mut,
+                Some(ident) if ident == "into_value" => &mut self.into_value,
+                Some(ident) if ident == "try_from_enum" => &mut self.try_from_enum,
+                _ => {
+                    return Err(meta.error(
+                        "expected `from`, `value`, `value_mut`, `into_value` or `try_from_enum`",
+                    ));
+                }
+            };
+            *family = enabled;
+            Ok(())
+        })
+    }
+}
+
+/// Settings for the generated serde representation
+#[derive(Default)]
+pub struct SerdeAttrs {
+    /// Field holding the variant's tag, `"type"` if not set
+    pub tag: Option<LitStr>,
+    /// Field holding the payload, `"value"` if not set
+    pub content: Option<LitStr>,
+    /// Case convention applied to every tag
+    pub rename_all: Option<LitStr>,
+}
+
+/// Case conventions accepted by `rename_all`, as spelled by serde
+const RENAME_RULES: &[&str] = &[
+    "lowercase",
+    "UPPERCASE",
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "kebab-case",
+    "SCREAMING-KEBAB-CASE",
+];
+
+/// Settings for the generated actix integration
+#[derive(Default)]
+pub struct ActixAttrs {
+    /// `Message::Result` shared by the enum and every payload type, `()` if not set
+    pub result: Option<Type>,
+}
+
+/// How `TryFrom<Bson>` matches values to payload types
+#[derive(Default)]
+pub struct BsonAttrs {
+    /// After an exact match fails, accept lossless conversions like `Int32` to `i64`
+    pub coerce: bool,
+}
+
+/// The validator a variant's payload must pass, with the error type it returns
+pub struct ValidateAttrs {
+    /// Function taking `&Payload` and returning `Result<(), Error>`
+    pub with: Path,
+    pub error: Type,
+}
+
+/// Name and visibility of the module holding the companion items
+#[derive(Default)]
+pub struct ModuleAttrs {
+    pub name: Option<Ident>,
+    pub vis: Option<Visibility>,
+}
+
+impl ContainerAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut parsed = ContainerAttrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("type_enum")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate") {
+                    parsed.crate_path = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("test_roundtrip") {
+                    parsed.test_roundtrip = true;
+                    if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                        meta.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("format") {
+                                parsed.roundtrip_format =
+                                    Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                                Ok(())
+                            } else {
+                                Err(meta.error("expected `format`"))
+                            }
+                        })?;
+                    }
+                    Ok(())
+                } else if meta.path.is_ident("companion_derive") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let paths: Punctuated<Path, Token![,]> =
+                        content.parse_terminated(Path::parse_mod_style, Token![,])?;
+                    parsed.companion_derives.extend(paths);
+                    Ok(())
+                } else if meta.path.is_ident("kind_name") {
+                    parsed.kind_name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("ref_enum") {
+                    parsed.ref_enum = true;
+                    Ok(())
+                } else if meta.path.is_ident("ref_name") {
+                    parsed.ref_name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    parsed.ref_enum = true;
+                    Ok(())
+                } else if meta.path.is_ident("mut_enum") {
+                    parsed.mut_enum = true;
+                    Ok(())
+                } else if meta.path.is_ident("mut_name") {
+                    parsed.mut_name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    parsed.mut_enum = true;
+                    Ok(())
+                } else if meta.path.is_ident("owned") {
+                    parsed.owned = true;
+                    Ok(())
+                } else if meta.path.is_ident("owned_name") {
+                    parsed.owned_name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    parsed.owned = true;
+                    Ok(())
+                } else if meta.path.is_ident("module") {
+                    let mut module = ModuleAttrs::default();
+                    if meta.input.peek(Token![=]) {
+                        module.name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                        meta.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("name") {
+                                module.name = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                                Ok(())
+                            } else if meta.path.is_ident("vis") {
+                                module.vis = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                                Ok(())
+                            } else {
+                                Err(meta.error("expected `name` or `vis`"))
+                            }
+                        })?;
+                    }
+                    parsed.module = Some(module);
+                    Ok(())
+                } else if meta.path.is_ident("parts") {
+                    parsed.parts = true;
+                    Ok(())
+                } else if meta.path.is_ident("partition") {
+                    parsed.partition = true;
+                    Ok(())
+                } else if meta.path.is_ident("visitor") {
+                    parsed.visitor = true;
+                    Ok(())
+                } else if meta.path.is_ident("async_visitor") {
+                    parsed.async_visitor = true;
+                    Ok(())
+                } else if meta.path.is_ident("handler") {
+                    parsed.handler = true;
+                    Ok(())
+                } else if meta.path.is_ident("bson") {
+                    let mut bson = BsonAttrs::default();
+                    if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                        meta.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("coerce") {
+                                bson.coerce = true;
+                                Ok(())
+                            } else if meta.path.is_ident("strict") {
+                                bson.coerce = false;
+                                Ok(())
+                            } else {
+                                Err(meta.error("expected `strict` or `coerce`"))
+                            }
+                        })?;
+                    }
+                    parsed.bson = Some(bson);
+                    Ok(())
+                } else if meta.path.is_ident("otel") {
+                    parsed.otel = true;
+                    Ok(())
+                } else if meta.path.is_ident("subset_of") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let types: Punctuated<Type, Token![,]> =
+                        content.parse_terminated(Type::parse, Token![,])?;
+                    parsed.subset_of.extend(types);
+                    Ok(())
+                } else if meta.path.is_ident("transitions") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    while !content.is_empty() {
+                        let mut from: Ident = content.parse()?;
+                        if !content.peek(Token![->]) {
+                            return Err(content.error("expected `->` after a state"));
+                        }
+                        while content.peek(Token![->]) {
+                            content.parse::<Token![->]>()?;
+                            let to: Ident = content.parse()?;
+                            parsed.transitions.push((from, to.clone()));
+                            from = to;
+                        }
+                        if content.is_empty() {
+                            break;
+                        }
+                        content.parse::<Token![,]>()?;
+                    }
+                    Ok(())
+                } else if meta.path.is_ident("actix") {
+                    let mut actix = ActixAttrs::default();
+                    if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                        meta.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("result") {
+                                actix.result = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                                Ok(())
+                            } else {
+                                Err(meta.error("expected `result`"))
+                            }
+                        })?;
+                    }
+                    parsed.actix = Some(actix);
+                    Ok(())
+                } else if meta.path.is_ident("auto_box_over") {
+                    parsed.auto_box_over = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("serde") {
+                    let mut serde = SerdeAttrs::default();
+                    if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                        meta.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("tag") {
+                                serde.tag = Some(meta.value()?.parse()?);
+                                Ok(())
+                            } else if meta.path.is_ident("content") {
+                                serde.content = Some(meta.value()?.parse()?);
+                                Ok(())
+                            } else if meta.path.is_ident("rename_all") {
+                                let rule: LitStr = meta.value()?.parse()?;
+                                if !RENAME_RULES.contains(&rule.value().as_str()) {
+                                    return Err(syn::Error::new_spanned(
+                                        &rule,
+                                        format!(
+                                            "unknown rename rule, expected one of: {}",
+                                            RENAME_RULES.join(", ")
+                                        ),
+                                    ));
+                                }
+                                serde.rename_all = Some(rule);
+                                Ok(())
+                            } else {
+                                Err(meta.error("expected `tag`, `content` or `rename_all`"))
+                            }
+                        })?;
+                    }
+                    parsed.serde = Some(serde);
+                    Ok(())
+                } else if meta.path.is_ident("must_use") {
+                    parsed.must_use = Some(meta.value()?.parse::<LitBool>()?.value);
+                    Ok(())
+                } else if meta.path.is_ident("redis") {
+                    parsed.redis = true;
+                    Ok(())
+                } else if meta.path.is_ident("ts") {
+                    parsed.ts = true;
+                    Ok(())
+                } else if meta.path.is_ident("telemetry") {
+                    parsed.telemetry = true;
+                    Ok(())
+                } else if meta.path.is_ident("error") {
+                    parsed.error = true;
+                    Ok(())
+                } else if meta.path.is_ident("display") {
+                    parsed.display = true;
+                    Ok(())
+                } else if meta.path.is_ident("is_methods") {
+                    parsed.is_methods = true;
+                    Ok(())
+                } else if meta.path.is_ident("const_constructors") {
+                    parsed.const_constructors = true;
+                    Ok(())
+                } else if meta.path.is_ident("discriminant") {
+                    parsed.discriminant = true;
+                    Ok(())
+                } else if meta.path.is_ident("getters") {
+                    parsed.getters = true;
+                    Ok(())
+                } else if meta.path.is_ident("delegate_ord") {
+                    parsed.delegate_ord = true;
+                    Ok(())
+                } else if meta.path.is_ident("payload_eq") {
+                    parsed.payload_eq = true;
+                    Ok(())
+                } else if meta.path.is_ident("payload_cmp") {
+                    parsed.payload_cmp = true;
+                    Ok(())
+                } else if meta.path.is_ident("only") {
+                    let mut families = Families {
+                        span: meta.path.span(),
+                        from: false,
+                        value: false,
+                        value_mut: false,
+                        into_value: false,
+                        try_from_enum: false,
+                    };
+                    families.parse_list(&meta, true)?;
+                    parsed.families = families;
+                    Ok(())
+                } else if meta.path.is_ident("delegate_ops") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let ops: Punctuated<Ident, Token![,]> =
+                        content.parse_terminated(Ident::parse, Token![,])?;
+                    parsed.delegate_ops.extend(ops);
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown type_enum attribute"))
+                }
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Options set on a single variant with `#[type_enum(...)]`
+#[derive(Default)]
+pub struct VariantAttrs {
+    /// Exclude the variant from all generated impls, always set for unit variants
+    pub skip: bool,
+    /// Exclude the variant from operators generated by `delegate_ops`
+    pub no_ops: bool,
+    /// Exclude the variant from comparisons generated by `payload_eq` and `payload_cmp`
+    pub no_cmp: bool,
+    /// Trait families the payload is left out of with `no_from` and `skip(...)`, as the
+    /// families it keeps
+    pub families: Families,
+    /// Exclude the variant from the generated serde representation
+    pub serde_skip: bool,
+    /// Format string of the variant in the generated `Display`
+    pub display: Option<LitStr>,
+    /// Name used instead of the variant's in generated methods, the kind and serialized tags
+    pub rename: Option<LitStr>,
+    /// Description returned by the kind's `description()`
+    pub doc: Option<LitStr>,
+    /// Convert from and to the `T` of a `Box<T>` field as well
+    pub boxed: bool,
+    /// Convert from and borrow the `T` of an `Arc<T>` or `Rc<T>` field as well
+    pub shared: bool,
+    /// Convert from the payload types of the derived enum the variant holds as well
+    pub flatten: bool,
+    /// Hold the values `from_any()` finds no other variant for, in a `Box<dyn Any>`
+    pub catch_all: bool,
+    /// Include the `Box<dyn Trait>` payload in `downcast_value()`, for traits with `Any` as a
+    /// supertrait
+    pub downcast: bool,
+    /// The type the payload is an alias of, for duplicate detection
+    pub same_as: Option<Type>,
+    /// Get the conversions for a payload type other variants hold as well, skipping those
+    pub primary: bool,
+    /// Build the enum's `Default` from this variant with default fields
+    pub default: bool,
+    /// More types converting into the enum through this variant, with `Into` on the payload, from
+    /// `from(...)` or its numeric spelling `coerce(...)`
+    pub from: Vec<Type>,
+    /// Types converting into the enum through this variant, with `TryFrom` on the payload
+    pub try_from: Vec<Type>,
+    /// Types the payload also lends out through `AsRef`, like `str` for a `String`
+    pub as_ref: Vec<Type>,
+    /// Stable numeric tag returned by `tag()`, independent of the declaration order
+    pub tag: Option<LitInt>,
+    /// Validator run by the `TryFrom` impl and `try_new_<variant>()` replacing `From`
+    pub validate: Option<ValidateAttrs>,
+}
+
+impl VariantAttrs {
+    pub fn parse(variant: &Variant) -> syn::Result<Self> {
+        let mut parsed = VariantAttrs::default();
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("type_enum"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    if meta.input.peek(syn::token::Paren) {
+                        parsed.families.parse_list(&meta, false)
+                    } else {
+                        parsed.skip = true;
+                        Ok(())
+                    }
+                } else if meta.path.is_ident("no_ops") {
+                    parsed.no_ops = true;
+                    Ok(())
+                } else if meta.path.is_ident("no_cmp") {
+                    parsed.no_cmp = true;
+                    Ok(())
+                } else if meta.path.is_ident("no_from") {
+                    parsed.families.from = false;
+                    Ok(())
+                } else if meta.path.is_ident("boxed") {
+                    parsed.boxed = true;
+                    Ok(())
+                } else if meta.path.is_ident("shared") {
+                    parsed.shared = true;
+                    Ok(())
+                } else if meta.path.is_ident("catch_all") {
+                    parsed.catch_all = true;
+                    Ok(())
+                } else if meta.path.is_ident("downcast") {
+                    parsed.downcast = true;
+                    Ok(())
+                } else if meta.path.is_ident("flatten") {
+                    parsed.flatten = true;
+                    Ok(())
+                } else if meta.path.is_ident("from") || meta.path.is_ident("coerce") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let types: Punctuated<Type, Token![,]> =
+                        content.parse_terminated(Type::parse, Token![,])?;
+                    parsed.from.extend(types);
+                    Ok(())
+                } else if meta.path.is_ident("try_from") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let types: Punctuated<Type, Token![,]> =
+                        content.parse_terminated(Type::parse, Token![,])?;
+                    parsed.try_from.extend(types);
+                    Ok(())
+                } else if meta.path.is_ident("as_ref") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let types: Punctuated<Type, Token![,]> =
+                        content.parse_terminated(Type::parse, Token![,])?;
+                    parsed.as_ref.extend(types);
+                    Ok(())
+                } else if meta.path.is_ident("validate") {
+                    let (mut with, mut error) = (None, None);
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("with") {
+                            with = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                            Ok(())
+                        } else if meta.path.is_ident("error") {
+                            error = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                            Ok(())
+                        } else {
+                            Err(meta.error("expected `with` or `error`"))
+                        }
+                    })?;
+                    let (Some(with), Some(error)) = (with, error) else {
+                        return Err(meta.error(
+                            "`validate` needs the validator and its error type, like \
+                            validate(with = \"check_port\", error = \"PortError\")",
+                        ));
+                    };
+                    parsed.validate = Some(ValidateAttrs { with, error });
+                    // Unchecked conversions and changes would get around the validator
+                    parsed.families.from = false;
+                    parsed.families.value_mut = false;
+                    Ok(())
+                } else if meta.path.is_ident("tag") {
+                    let tag: LitInt = meta.value()?.parse()?;
+                    tag.base10_parse::<u16>()?;
+                    parsed.tag = Some(tag);
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    parsed.default = true;
+                    Ok(())
+                } else if meta.path.is_ident("primary") {
+                    parsed.primary = true;
+                    Ok(())
+                } else if meta.path.is_ident("same_as") {
+                    parsed.same_as = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    let rename: LitStr = meta.value()?.parse()?;
+                    if syn::parse_str::<Ident>(&crate::camel_case(&rename.value())).is_err() {
+                        return Err(syn::Error::new_spanned(
+                            &rename,
+                            "`rename` needs a name like \"json_text\", made of letters, digits \
+                            and underscores",
+                        ));
+                    }
+                    parsed.rename = Some(rename);
+                    Ok(())
+                } else if meta.path.is_ident("display") {
+                    parsed.display = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("doc") {
+                    parsed.doc = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("serde") {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("skip") {
+                            parsed.serde_skip = true;
+                            Ok(())
+                        } else {
+                            Err(meta.error("expected `skip`"))
+                        }
+                    })
+                } else {
+                    Err(meta.error("unknown type_enum attribute"))
+                }
+            })?;
+        }
+        Ok(parsed)
+    }
+}
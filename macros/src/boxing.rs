@@ -0,0 +1,249 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, GenericArgument, PathArguments, Type};
+
+use crate::attrs::Families;
+use crate::{generics, normalize, payload_type, variant_with_fields};
+
+/// The `T` of a `Box<T>` field type
+pub fn boxed_type(ty: &Type) -> Option<&Type> {
+    pointee(ty, "Box")
+}
+
+/// The `T` of a field type like `Box<T>`, whose last path segment is `pointer`
+pub fn pointee<'t>(ty: &'t Type, pointer: &str) -> Option<&'t Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != pointer {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+/// Generate the impls for `#[type_enum(auto_box_over = N)]`
+///
+/// A derive can't change the enum's fields, so payloads are boxed by declaring them as `Box<T>`.
+/// Those variants also convert from and to the unboxed `T`, while every other payload gets a
+/// compile-time check that it is no larger than `limit` bytes.
+pub fn auto_box(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    limit: usize,
+    payload_variants: &[(&syn::Variant, Families)],
+    variants: &[&syn::Variant],
+    unwrapped: &[(&syn::Variant, Type)],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let mut items = Vec::new();
+    for &(variant, families) in payload_variants {
+        let field_type = single_type(variant);
+        match field_type.as_ref().and_then(boxed_type) {
+            Some(inner) => {
+                check_clash(variant, inner, variants, unwrapped)?;
+                items.push(unboxed_impls(
+                    input, krate, kind_path, families, variant, inner,
+                ));
+            }
+            None => {
+                let payload = payload_type(&variant.fields);
+                let message = format!(
+                    "the payload of `{}::{}` is larger than {} bytes; declare it as a `Box` \
+                    to keep the enum small",
+                    name, variant.ident, limit
+                );
+                items.push(quote! {
+                    const _: () = assert!(::core::mem::size_of::<#payload>() <= #limit, #message);
+                });
+            }
+        }
+    }
+
+    Ok(quote! { #(#items)* })
+}
+
+/// Generate the unboxed impls for the variants marked `#[type_enum(boxed)]`
+pub fn boxed(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    boxed: &[(&syn::Variant, Families)],
+    variants: &[&syn::Variant],
+    unwrapped: &[(&syn::Variant, Type)],
+) -> syn::Result<TokenStream> {
+    let mut items = Vec::new();
+    for &(variant, families) in boxed {
+        let field_type = single_type(variant);
+        let Some(inner) = field_type.as_ref().and_then(boxed_type) else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(boxed)]` needs `{}` to hold a single `Box<T>` field",
+                    variant.ident
+                ),
+            ));
+        };
+        check_clash(variant, inner, variants, unwrapped)?;
+        items.push(unboxed_impls(
+            input, krate, kind_path, families, variant, inner,
+        ));
+    }
+    Ok(quote! { #(#items)* })
+}
+
+pub fn single_type(variant: &syn::Variant) -> Option<Type> {
+    match variant.fields.len() {
+        1 => variant.fields.iter().next().map(|field| field.ty.clone()),
+        _ => None,
+    }
+}
+
+/// The types the boxed, shared and `Cow` variants also convert from, with their variant
+///
+/// With `auto_box`, every variant holding a `Box<T>` is taken as boxed.
+pub fn unwrapped_types<'v>(
+    payload_variants: &[(&'v syn::Variant, Families)],
+    auto_box: bool,
+    boxed: &[(&'v syn::Variant, Families)],
+    shared: &[(&'v syn::Variant, Families)],
+) -> Vec<(&'v syn::Variant, Type)> {
+    let mut unwrapped = Vec::new();
+    for &(variant, _) in payload_variants {
+        let Some(field_type) = single_type(variant) else {
+            continue;
+        };
+        let is_boxed = auto_box || boxed.iter().any(|(boxed, _)| boxed.ident == variant.ident);
+        let is_shared = shared
+            .iter()
+            .any(|(shared, _)| shared.ident == variant.ident);
+        if let Some(inner) = boxed_type(&field_type).filter(|_| is_boxed) {
+            unwrapped.push((variant, inner.clone()));
+        } else if let Some(inner) = (pointee(&field_type, "Arc"))
+            .or_else(|| pointee(&field_type, "Rc"))
+            .filter(|_| is_shared)
+        {
+            unwrapped.push((variant, inner.clone()));
+        } else if let Some((lifetime, borrowed)) = crate::cow::cow_type(&field_type) {
+            unwrapped.push((variant, syn::parse_quote!(&#lifetime #borrowed)));
+            if let Some(owned) = crate::cow::known_owned_type(borrowed) {
+                unwrapped.push((variant, owned));
+            }
+        }
+    }
+    unwrapped
+}
+
+/// Check that no other variant holds the unwrapped type, or unwraps to it, whose impls would
+/// conflict
+pub fn check_clash(
+    variant: &syn::Variant,
+    inner: &Type,
+    variants: &[&syn::Variant],
+    unwrapped: &[(&syn::Variant, Type)],
+) -> syn::Result<()> {
+    let inner_key = normalize::type_key(inner);
+    let held = variants
+        .iter()
+        .find(|other| single_type(other).is_some_and(|ty| normalize::type_key(&ty) == inner_key));
+    if let Some(other) = held {
+        return Err(syn::Error::new_spanned(
+            variant,
+            format!(
+                "duplicate type in enum: `{}` unwraps to the type held by `{}`",
+                variant.ident, other.ident
+            ),
+        ));
+    }
+    let also_unwrapped = unwrapped
+        .iter()
+        .find(|(other, ty)| other.ident != variant.ident && normalize::type_key(ty) == inner_key);
+    match also_unwrapped {
+        Some((other, _)) => Err(syn::Error::new_spanned(
+            variant,
+            format!(
+                "duplicate type in enum: `{}` unwraps to the same type as `{}`",
+                variant.ident, other.ident
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Conversions from and to `inner` for a variant holding a `Box<inner>`
+fn unboxed_impls(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    families: Families,
+    variant: &syn::Variant,
+    inner: &Type,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let lifetime = generics::accessor_lifetime(&input.generics);
+    let accessor_generics = generics::with_accessor_lifetime(&input.generics, &lifetime);
+    let (accessor_impl_generics, _, _) = accessor_generics.split_for_impl();
+    let kind_variant = crate::kind_ident(variant);
+    let pattern = variant_with_fields(&quote!(#name), variant, &[quote!(val)]);
+    let constructor = variant_with_fields(&quote!(#name), variant, &[quote!(Box::new(value))]);
+
+    let mut items = Vec::new();
+    if families.from {
+        items.push(quote! {
+            impl #impl_generics From<#inner> for #name #ty_generics #where_clause {
+                fn from(value: #inner) -> Self {
+                    #constructor
+                }
+            }
+
+            impl #impl_generics #krate::Payload<#name #ty_generics> for #inner #where_clause {
+                const KIND: #kind_path = #kind_path::#kind_variant;
+            }
+        });
+    }
+    if families.value {
+        items.push(quote! {
+            impl #accessor_impl_generics #krate::Value<#lifetime, &#lifetime #inner> for #name #ty_generics #where_clause {
+                fn value(&#lifetime self) -> Option<&#lifetime #inner> {
+                    match self {
+                        #pattern => Some(val),
+                        _ => None,
+                    }
+                }
+            }
+        });
+    }
+    if families.value_mut {
+        items.push(quote! {
+            impl #accessor_impl_generics #krate::ValueMut<#lifetime, &#lifetime mut #inner> for #name #ty_generics #where_clause {
+                fn value_mut(&#lifetime mut self) -> Option<&#lifetime mut #inner> {
+                    match self {
+                        #pattern => Some(val),
+                        _ => None,
+                    }
+                }
+            }
+        });
+    }
+    if families.into_value {
+        items.push(quote! {
+            impl #impl_generics #krate::IntoValue<#inner> for #name #ty_generics #where_clause {
+                fn into_value(self) -> Result<#inner, Self> {
+                    match self {
+                        #pattern => Ok(*val),
+                        other => Err(other),
+                    }
+                }
+            }
+        });
+    }
+    quote! { #(#items)* }
+}
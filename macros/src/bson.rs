@@ -0,0 +1,121 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{Fields, Ident};
+
+use crate::attrs::{BsonAttrs, ValidateAttrs};
+use crate::{field_bindings, payload_type, probed_return, variant_with_fields};
+
+/// Generate `From<Enum> for Bson` covering every variant, and `TryFrom<Bson> for Enum` probing
+/// the payload types of `variants` in declaration order
+///
+/// A validated variant only takes payloads passing its validator.
+pub fn bson_impls(
+    name: &Ident,
+    krate: &TokenStream,
+    all_variants: &[&syn::Variant],
+    variants: &[(&syn::Variant, Option<&ValidateAttrs>)],
+    attrs: &BsonAttrs,
+) -> TokenStream {
+    let ty = quote!(#name);
+    let to_arms = all_variants.iter().map(|variant| {
+        let bindings = field_bindings(&variant.fields);
+        let pattern = variant_with_fields(&ty, variant, &bindings);
+        let value = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                quote!(#krate::__private::bson::Bson::from(field_0))
+            }
+            Fields::Unnamed(_) => quote! {
+                #krate::__private::bson::Bson::Array(vec![
+                    #(#krate::__private::bson::Bson::from(#bindings)),*
+                ])
+            },
+            Fields::Named(fields) => {
+                let keys = fields
+                    .named
+                    .iter()
+                    .map(|f| f.ident.as_ref().unwrap().unraw().to_string());
+                quote! {
+                    #krate::__private::bson::Bson::Document(#krate::__private::bson::doc! {
+                        #(#keys: #bindings),*
+                    })
+                }
+            }
+            Fields::Unit => quote!(#krate::__private::bson::Bson::Null),
+        };
+        quote!(#pattern => #value)
+    });
+
+    let probes = |method: TokenStream| {
+        let probes = variants.iter().map(|&(variant, validator)| {
+            let returns = probed_return(&ty, variant, validator);
+            let Fields::Named(fields) = &variant.fields else {
+                let payload = payload_type(&variant.fields);
+                return quote! {
+                    if let Some(payload) =
+                        <#payload as #krate::bson::FromBson>::#method(&value)
+                    {
+                        #returns
+                    }
+                };
+            };
+            // Named fields were written as a document, so they are read back by key
+            let keys = fields
+                .named
+                .iter()
+                .map(|f| f.ident.as_ref().unwrap().unraw().to_string());
+            let types = fields.named.iter().map(|f| &f.ty);
+            let bindings = field_bindings(&variant.fields);
+            let len = bindings.len();
+            let payload = match &bindings[..] {
+                [binding] => quote!(#binding),
+                _ => quote!((#(#bindings),*)),
+            };
+            quote! {
+                if let #krate::__private::bson::Bson::Document(document) = &value {
+                    if document.len() == #len {
+                        if let (#(Some(#bindings),)*) = (#(
+                            document
+                                .get(#keys)
+                                .and_then(<#types as #krate::bson::FromBson>::#method),
+                        )*) {
+                            let payload = #payload;
+                            #returns
+                        }
+                    }
+                }
+            }
+        });
+        quote! { #(#probes)* }
+    };
+    let strict = probes(quote!(from_bson_strict));
+    let coerce = if attrs.coerce {
+        probes(quote!(from_bson_coerce))
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        impl From<#name> for #krate::__private::bson::Bson {
+            fn from(value: #name) -> Self {
+                match value {
+                    #(#to_arms,)*
+                }
+            }
+        }
+
+        impl TryFrom<#krate::__private::bson::Bson> for #name {
+            type Error = #krate::__private::bson::Bson;
+
+            /// Convert into the first variant whose payload type matches the value, handing the
+            /// value back if none does
+            fn try_from(
+                value: #krate::__private::bson::Bson,
+            ) -> Result<Self, #krate::__private::bson::Bson> {
+                #strict
+                #coerce
+                Err(value)
+            }
+        }
+    }
+}
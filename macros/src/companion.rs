@@ -0,0 +1,1393 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    DeriveInput, Fields, Ident, LitInt, LitStr, Path, Token, Type, TypeParamBound, Visibility,
+    ext::IdentExt, parse_quote, punctuated::Punctuated,
+};
+
+use crate::attrs::{ContainerAttrs, ValidateAttrs};
+
+/// Integer types accepted by `#[repr(...)]`
+const PRIMITIVE_REPRS: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// Build the `#[derive(...)]` attribute for a companion type
+///
+/// `builtin` are the derives the macro always applies; any of the user's extra derives naming
+/// the same trait are dropped so they don't produce conflicting impls.
+pub fn derive_attr(builtin: &[&str], extra: &[Path]) -> TokenStream {
+    let builtin_paths = builtin.iter().map(|name| format_ident!("{}", name));
+    let extra = extra.iter().filter(|path| {
+        path.segments
+            .last()
+            .is_none_or(|segment| !builtin.iter().any(|name| segment.ident == name))
+    });
+    let derives: Vec<TokenStream> = builtin_paths
+        .map(|ident| quote!(#ident))
+        .chain(extra.map(|path| quote!(#path)))
+        .collect();
+    if derives.is_empty() {
+        quote! {}
+    } else {
+        quote! { #[derive(#(#derives),*)] }
+    }
+}
+
+/// Shared settings for generating companion items
+pub struct Companions<'a> {
+    input: &'a DeriveInput,
+    /// Every variant of the enum, skipped ones included
+    variants: Vec<&'a syn::Variant>,
+    derives: &'a [Path],
+    /// Module wrapping the companion items, and its visibility
+    module: Option<(Ident, Visibility)>,
+    /// Visibility of the companion items, adjusted for the module if there is one
+    vis: Visibility,
+    /// Whether extraction methods get `#[must_use]`
+    must_use: bool,
+    /// `#[non_exhaustive]` if the enum has it, for the companion enums mirroring its variants
+    non_exhaustive: TokenStream,
+}
+
+impl<'a> Companions<'a> {
+    pub fn new(
+        input: &'a DeriveInput,
+        variants: Vec<&'a syn::Variant>,
+        attrs: &'a ContainerAttrs,
+    ) -> Self {
+        let module = attrs.module.as_ref().map(|module| {
+            let name = module
+                .name
+                .clone()
+                .unwrap_or_else(|| format_ident!("{}_types", crate::snake_case(&input.ident)));
+            let vis = module.vis.clone().unwrap_or_else(|| input.vis.clone());
+            (name, vis)
+        });
+        let vis = if module.is_some() {
+            nested_vis(&input.vis)
+        } else {
+            input.vis.clone()
+        };
+        Companions {
+            input,
+            variants,
+            derives: &attrs.companion_derives,
+            module,
+            vis,
+            must_use: attrs.must_use.unwrap_or(true),
+            non_exhaustive: if input
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("non_exhaustive"))
+            {
+                quote! { #[non_exhaustive] }
+            } else {
+                quote! {}
+            },
+        }
+    }
+
+    /// `#[must_use]` for a generated extraction method, unless turned off
+    fn must_use(&self) -> TokenStream {
+        if self.must_use {
+            quote! { #[must_use] }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Path to a companion item from the scope of the enum
+    pub fn path(&self, ident: &Ident) -> TokenStream {
+        match &self.module {
+            Some((module, _)) => quote!(#module::#ident),
+            None => quote!(#ident),
+        }
+    }
+
+    /// Emit the companion items, inside the generated module if one was requested
+    pub fn wrap(&self, items: Vec<TokenStream>) -> TokenStream {
+        match &self.module {
+            Some((module, vis)) => {
+                let doc = format!("Companion items generated for [`{}`]", self.input.ident);
+                quote! {
+                    #[doc = #doc]
+                    #vis mod #module {
+                        use super::*;
+
+                        #(#items)*
+                    }
+                }
+            }
+            None => quote! { #(#items)* },
+        }
+    }
+
+    /// Generate the fieldless `<Name>Kind` enum with its `description()` method, and the `kind()`
+    /// method returning it
+    ///
+    /// `descriptions` holds each variant's `#[type_enum(doc = "...")]` string, in declaration
+    /// order.
+    pub fn kind_enum(&self, kind_name: &Ident, descriptions: &[Option<LitStr>]) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let derive = derive_attr(
+            &[
+                "Debug",
+                "Clone",
+                "Copy",
+                "PartialEq",
+                "Eq",
+                "PartialOrd",
+                "Ord",
+                "Hash",
+            ],
+            self.derives,
+        );
+        let variant_names: Vec<_> = self.variants.iter().map(|variant| &variant.ident).collect();
+        let kind_variants: Vec<_> = self.variants.iter().map(|v| crate::kind_ident(v)).collect();
+        let kind_variant_items = self
+            .variants
+            .iter()
+            .zip(&kind_variants)
+            .map(|(variant, kind)| {
+                let deprecated = crate::deprecated_attrs(variant);
+                quote!(#(#deprecated)* #kind)
+            });
+        let enum_doc = format!("The variants of [`{}`], without their payloads", name);
+        let kind_doc = format!("Which variant of [`{}`] this value holds", name);
+        let must_use = self.must_use();
+        let non_exhaustive = &self.non_exhaustive;
+        let descriptions = descriptions.iter().map(|doc| match doc {
+            Some(doc) => quote!(Some(#doc)),
+            None => quote!(None),
+        });
+
+        quote! {
+            #[doc = #enum_doc]
+            #derive
+            #non_exhaustive
+            #vis enum #kind_name {
+                #(#kind_variant_items),*
+            }
+
+            impl #kind_name {
+                /// The description given to the variant with `#[type_enum(doc = "...")]`, if any
+                #must_use
+                #vis const fn description(self) -> Option<&'static str> {
+                    match self {
+                        #(#kind_name::#kind_variants => #descriptions),*
+                    }
+                }
+            }
+
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #kind_doc]
+                #must_use
+                #vis fn kind(&self) -> #kind_name {
+                    match self {
+                        #(#name::#variant_names { .. } => #kind_name::#kind_variants),*
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate `discriminant()` returning each variant's declared discriminant, typed as the
+    /// primitive or `C` `#[repr]`, or as `isize` without one
+    ///
+    /// Implicit discriminants count up from the previous one, like the compiler assigns them.
+    pub fn discriminant(&self) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let reprs: Vec<Ident> = self
+            .input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("repr"))
+            .filter_map(|attr| {
+                attr.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+                    .ok()
+            })
+            .flatten()
+            .collect();
+        let primitive = reprs
+            .iter()
+            .find(|ident| PRIMITIVE_REPRS.iter().any(|repr| *ident == repr));
+        // A plain `#[repr(C)]` stores the discriminant as a C `int`
+        let c_repr = reprs.iter().any(|ident| ident == "C");
+        let repr = match primitive {
+            Some(repr) => quote!(#repr),
+            None if c_repr => quote!(::core::ffi::c_int),
+            None => quote!(isize),
+        };
+
+        let mut previous: Option<TokenStream> = None;
+        let arms = self.variants.iter().map(|variant| {
+            let variant_name = &variant.ident;
+            let value = match (&variant.discriminant, &previous) {
+                (Some((_, expr)), _) => quote!((#expr)),
+                (None, Some(previous)) => quote!((#previous + 1)),
+                (None, None) => quote!(0),
+            };
+            previous = Some(value.clone());
+            quote!(#name::#variant_name { .. } => #value)
+        });
+        let doc = format!(
+            "The discriminant declared for the variant this [`{}`] holds",
+            name
+        );
+        let must_use = self.must_use();
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #doc]
+                #must_use
+                #vis const fn discriminant(&self) -> #repr {
+                    match self {
+                        #(#arms),*
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate `tag()` on the enum and its kind, and the kind's `from_tag()`, if the variants
+    /// declare `#[type_enum(tag = N)]`
+    ///
+    /// `tags` holds each variant's tag, in declaration order. Once one variant has a tag, all of
+    /// them need one, and no two can share it.
+    pub fn tags(&self, kind_name: &Ident, tags: &[Option<LitInt>]) -> syn::Result<TokenStream> {
+        if tags.iter().all(Option::is_none) {
+            return Ok(quote! {});
+        }
+        let mut seen: Vec<(u16, &Ident)> = Vec::new();
+        for (variant, tag) in self.variants.iter().zip(tags) {
+            let Some(tag) = tag else {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "`{}` needs a #[type_enum(tag = N)] like the other variants",
+                        variant.ident
+                    ),
+                ));
+            };
+            let value = tag.base10_parse::<u16>()?;
+            if let Some((_, first)) = seen.iter().find(|(seen, _)| *seen == value) {
+                return Err(syn::Error::new_spanned(
+                    tag,
+                    format!(
+                        "variants `{}` and `{}` both have the tag {}",
+                        first, variant.ident, value
+                    ),
+                ));
+            }
+            seen.push((value, &variant.ident));
+        }
+
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let must_use = self.must_use();
+        let variant_names: Vec<_> = self.variants.iter().map(|variant| &variant.ident).collect();
+        let kind_variants: Vec<_> = self.variants.iter().map(|v| crate::kind_ident(v)).collect();
+        let tags: Vec<u16> = seen.iter().map(|(tag, _)| *tag).collect();
+        let doc = format!("The tag declared for the variant this [`{}`] holds", name);
+
+        Ok(quote! {
+            impl #kind_name {
+                /// The tag declared for the variant with `#[type_enum(tag = N)]`
+                #must_use
+                #vis const fn tag(self) -> u16 {
+                    match self {
+                        #(#kind_name::#kind_variants => #tags),*
+                    }
+                }
+
+                /// The variant declared with the given tag, if any
+                #must_use
+                #vis const fn from_tag(tag: u16) -> Option<Self> {
+                    match tag {
+                        #(#tags => Some(#kind_name::#kind_variants),)*
+                        _ => None,
+                    }
+                }
+            }
+
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #doc]
+                #must_use
+                #vis const fn tag(&self) -> u16 {
+                    match self {
+                        #(#name::#variant_names { .. } => #tags),*
+                    }
+                }
+            }
+        })
+    }
+
+    /// Generate the borrowed `<Name>Ref<'a>` mirror enum and the `as_ref()` method returning it
+    pub fn ref_enum(&self, ref_name: &Ident) -> TokenStream {
+        // Every field is a shared reference, so the view is always `Copy`
+        let derive = derive_attr(&["Clone", "Copy"], self.derives);
+        self.mirror_enum(ref_name, derive, false)
+    }
+
+    /// Generate the mutably borrowed `<Name>Mut<'a>` mirror enum and the `as_mut()` method
+    /// returning it
+    pub fn mut_enum(&self, mut_name: &Ident) -> TokenStream {
+        // `&mut T` fields are neither `Clone` nor `Copy`
+        let derives: Vec<Path> = self
+            .derives
+            .iter()
+            .filter(|path| {
+                path.segments
+                    .last()
+                    .is_none_or(|segment| segment.ident != "Clone" && segment.ident != "Copy")
+            })
+            .cloned()
+            .collect();
+        self.mirror_enum(mut_name, derive_attr(&[], &derives), true)
+    }
+
+    /// Generate the `<Name>Owned` enum holding the owned type of each borrowed payload, with
+    /// `to_owned()` on the enum and `borrow()` on the owned enum converting between them
+    ///
+    /// `&'a T` and `Cow<'a, T>` fields become the owned type of `T`, like `String` for `str`;
+    /// other fields are kept and cloned.
+    pub fn owned_enum(&self, owned_name: &Ident) -> syn::Result<TokenStream> {
+        let name = &self.input.ident;
+        let vis = &self.vis;
+        let generics = &self.input.generics;
+        let lifetime = match generics.params.iter().collect::<Vec<_>>()[..] {
+            [syn::GenericParam::Lifetime(param)] => &param.lifetime,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &self.input.ident,
+                    format!(
+                        "`#[type_enum(owned)]` needs `{}` to have a single lifetime parameter, \
+                        like `{}<'a>`",
+                        name, name
+                    ),
+                ));
+            }
+        };
+        let derive = derive_attr(&[], self.derives);
+        let non_exhaustive = &self.non_exhaustive;
+        let must_use = self.must_use();
+        let enum_doc = format!("An owned copy of a [`{}`]", name);
+        let to_owned_doc = format!("Copy the borrowed payload into a [`{}`]", owned_name);
+        let borrow_doc = format!("Borrow the held value as a [`{}`]", name);
+        let ty = quote!(#name);
+        let owned_ty = quote!(#owned_name);
+
+        let mut owned_variants = Vec::new();
+        let mut to_owned_arms = Vec::new();
+        let mut borrow_arms = Vec::new();
+        for variant in &self.variants {
+            let bindings = crate::field_bindings(&variant.fields);
+            let mut owned_fields = Vec::new();
+            let mut to_owned = Vec::new();
+            let mut borrow = Vec::new();
+            for (field, binding) in variant.fields.iter().zip(&bindings) {
+                let ty = &field.ty;
+                let borrowed = match ty {
+                    Type::Reference(reference)
+                        if reference.mutability.is_none()
+                            && reference.lifetime.as_ref() == Some(lifetime) =>
+                    {
+                        Some((&*reference.elem, false))
+                    }
+                    _ => crate::cow::cow_type(ty)
+                        .filter(|(cow_lifetime, _)| *cow_lifetime == lifetime)
+                        .map(|(_, borrowed)| (borrowed, true)),
+                };
+                match borrowed {
+                    Some((borrowed, is_cow)) => {
+                        let owned = crate::cow::owned_type(borrowed);
+                        to_owned.push(quote! {
+                            ::std::borrow::ToOwned::to_owned(&**#binding)
+                        });
+                        let view = quote!(::core::borrow::Borrow::<#borrowed>::borrow(#binding));
+                        borrow.push(if is_cow {
+                            quote!(::std::borrow::Cow::Borrowed(#view))
+                        } else {
+                            view
+                        });
+                        owned_fields.push(owned);
+                    }
+                    None if mentions_lifetime(ty.to_token_stream(), lifetime) => {
+                        return Err(syn::Error::new_spanned(
+                            ty,
+                            format!(
+                                "`#[type_enum(owned)]` can only replace `&{} T` and \
+                                `Cow<{}, T>` fields with their owned type",
+                                lifetime, lifetime
+                            ),
+                        ));
+                    }
+                    None => {
+                        to_owned.push(quote!(::core::clone::Clone::clone(#binding)));
+                        borrow.push(quote!(::core::clone::Clone::clone(#binding)));
+                        owned_fields.push(ty.clone());
+                    }
+                }
+            }
+
+            let variant_name = &variant.ident;
+            let deprecated = crate::deprecated_attrs(variant);
+            owned_variants.push(match &variant.fields {
+                Fields::Unnamed(_) => {
+                    quote! { #(#deprecated)* #variant_name(#(#owned_fields),*) }
+                }
+                Fields::Named(fields) => {
+                    let names = fields.named.iter().map(|f| &f.ident);
+                    quote! { #(#deprecated)* #variant_name { #(#names: #owned_fields),* } }
+                }
+                Fields::Unit => quote! { #(#deprecated)* #variant_name },
+            });
+            let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+            let owned_pattern = crate::variant_with_fields(&owned_ty, variant, &bindings);
+            let owned_constructor = crate::variant_with_fields(&owned_ty, variant, &to_owned);
+            let constructor = crate::variant_with_fields(&ty, variant, &borrow);
+            to_owned_arms.push(quote!(#pattern => #owned_constructor));
+            borrow_arms.push(quote!(#owned_pattern => #constructor));
+        }
+
+        Ok(quote! {
+            #[doc = #enum_doc]
+            #derive
+            #non_exhaustive
+            #vis enum #owned_name {
+                #(#owned_variants),*
+            }
+
+            impl<#lifetime> #name<#lifetime> {
+                #[doc = #to_owned_doc]
+                #must_use
+                #vis fn to_owned(&self) -> #owned_name {
+                    match self {
+                        #(#to_owned_arms),*
+                    }
+                }
+            }
+
+            impl #owned_name {
+                #[doc = #borrow_doc]
+                #must_use
+                #vis fn borrow(&self) -> #name<'_> {
+                    match self {
+                        #(#borrow_arms),*
+                    }
+                }
+            }
+        })
+    }
+
+    fn mirror_enum(&self, mirror_name: &Ident, derive: TokenStream, mutable: bool) -> TokenStream {
+        let name = &self.input.ident;
+        let vis = &self.vis;
+        let (enum_doc, method_doc, method, reference, receiver) = if mutable {
+            (
+                format!("A mutably borrowed view of a [`{}`]", name),
+                format!("Borrow the held value mutably as a [`{}`]", mirror_name),
+                format_ident!("as_mut"),
+                quote!(&'a mut),
+                quote!(&mut self),
+            )
+        } else {
+            (
+                format!("A borrowed view of a [`{}`]", name),
+                format!("Borrow the held value as a [`{}`]", mirror_name),
+                format_ident!("as_ref"),
+                quote!(&'a),
+                quote!(&self),
+            )
+        };
+        let must_use = self.must_use();
+        let non_exhaustive = &self.non_exhaustive;
+
+        let mirror_variants = self.variants.iter().map(|variant| {
+            let variant_name = &variant.ident;
+            let deprecated = crate::deprecated_attrs(variant);
+            match &variant.fields {
+                Fields::Unnamed(fields) => {
+                    let types = fields.unnamed.iter().map(|f| &f.ty);
+                    quote! { #(#deprecated)* #variant_name(#(#reference #types),*) }
+                }
+                Fields::Named(fields) => {
+                    let names = fields.named.iter().map(|f| &f.ident);
+                    let types = fields.named.iter().map(|f| &f.ty);
+                    quote! { #(#deprecated)* #variant_name { #(#names: #reference #types),* } }
+                }
+                Fields::Unit => quote! { #(#deprecated)* #variant_name },
+            }
+        });
+
+        let arms = self.variants.iter().map(|variant| {
+            let variant_name = &variant.ident;
+            match &variant.fields {
+                Fields::Unnamed(fields) => {
+                    let bindings: Vec<_> = (0..fields.unnamed.len())
+                        .map(|i| format_ident!("field_{}", i))
+                        .collect();
+                    quote! { #name::#variant_name(#(#bindings),*) => #mirror_name::#variant_name(#(#bindings),*) }
+                }
+                Fields::Named(fields) => {
+                    let names: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+                    quote! { #name::#variant_name { #(#names),* } => #mirror_name::#variant_name { #(#names),* } }
+                }
+                Fields::Unit => quote! { #name::#variant_name => #mirror_name::#variant_name },
+            }
+        });
+
+        quote! {
+            #[doc = #enum_doc]
+            #derive
+            #non_exhaustive
+            #vis enum #mirror_name<'a> {
+                #(#mirror_variants),*
+            }
+
+            impl #name {
+                #[doc = #method_doc]
+                #must_use
+                #vis fn #method(#receiver) -> #mirror_name<'_> {
+                    match self {
+                        #(#arms),*
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate the `<Name>Partition` struct with a `Vec` of payloads per variant, filled in one
+    /// pass by `partition()`
+    ///
+    /// Each field is named after its variant and holds the variant's payload type, so unit
+    /// variants collect `()` values that count them.
+    pub fn partition(&self) -> TokenStream {
+        let name = &self.input.ident;
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let partition_name = format_ident!("{}Partition", name);
+        let derive = derive_attr(&["Default"], self.derives);
+        let struct_doc = format!(
+            "The payloads of a sequence of [`{}`] values, split by variant",
+            name
+        );
+        let partition_doc = format!(
+            "Split `values` into a [`{}`], keeping their order within each variant",
+            partition_name
+        );
+
+        let mut fields = Vec::new();
+        let mut arms = Vec::new();
+        for variant in &self.variants {
+            let snake = crate::snake_case(&crate::kind_ident(variant));
+            // Keywords become raw identifiers, except those that can't be, like `crate`
+            let field = match snake.as_str() {
+                "crate" | "self" | "super" | "Self" => format_ident!("{}_", snake),
+                _ => syn::parse_str::<Ident>(&snake)
+                    .unwrap_or_else(|_| Ident::new_raw(&snake, variant.ident.span())),
+            };
+            let payload = crate::payload_type(&variant.fields);
+            let doc = format!("The payloads of the [`{}::{}`] values", name, variant.ident);
+            fields.push(quote! {
+                #[doc = #doc]
+                #vis #field: ::std::vec::Vec<#payload>
+            });
+            let bindings = crate::field_bindings(&variant.fields);
+            let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+            let payload_value = match &bindings[..] {
+                [binding] => quote!(#binding),
+                _ => quote!((#(#bindings),*)),
+            };
+            arms.push(quote! {
+                #pattern => self.#field.push(#payload_value)
+            });
+        }
+
+        quote! {
+            #[doc = #struct_doc]
+            #derive
+            #vis struct #partition_name {
+                #(#fields),*
+            }
+
+            impl ::core::iter::Extend<#name> for #partition_name {
+                fn extend<I: ::core::iter::IntoIterator<Item = #name>>(&mut self, values: I) {
+                    for value in values {
+                        match value {
+                            #(#arms),*
+                        }
+                    }
+                }
+            }
+
+            impl ::core::iter::FromIterator<#name> for #partition_name {
+                fn from_iter<I: ::core::iter::IntoIterator<Item = #name>>(values: I) -> Self {
+                    let mut partition = <Self as ::core::default::Default>::default();
+                    partition.extend(values);
+                    partition
+                }
+            }
+
+            impl #name {
+                #[doc = #partition_doc]
+                #vis fn partition(
+                    values: impl ::core::iter::IntoIterator<Item = Self>,
+                ) -> #partition_name {
+                    values.into_iter().collect()
+                }
+            }
+        }
+    }
+
+    /// Generate `into_parts()` splitting the enum into its kind and boxed payload, and
+    /// `from_parts()` reassembling it
+    ///
+    /// `validators` holds each variant's `#[type_enum(validate(...))]` validator, in declaration
+    /// order, which `from_parts()` runs on the payload.
+    pub fn parts(&self, kind_name: &Ident, validators: &[Option<&ValidateAttrs>]) -> TokenStream {
+        let name = &self.input.ident;
+        // Payloads are boxed as `dyn Any`, which requires them to be `'static`
+        let mut generics = self.input.generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(Self: 'static));
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let into_doc = format!(
+            "Split into the [`{}`] and the boxed payload; multi-field variants box a tuple",
+            kind_name
+        );
+        let from_doc = format!(
+            "Reassemble a [`{}`] from [`{}::into_parts`] output, handing the payload back if its \
+            type doesn't match `kind` or it fails the variant's validator",
+            name, name
+        );
+
+        let must_use = self.must_use();
+        let mut into_arms = Vec::new();
+        let mut from_arms = Vec::new();
+        for (variant, validator) in self.variants.iter().zip(validators) {
+            let kind_variant = crate::kind_ident(variant);
+            let bindings = crate::field_bindings(&variant.fields);
+            let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+            let payload = crate::payload_type(&variant.fields);
+            let value = if bindings.len() == 1 {
+                quote!(#(#bindings)*)
+            } else {
+                quote!((#(#bindings),*))
+            };
+            into_arms.push(quote! {
+                #pattern => (#kind_name::#kind_variant, ::std::boxed::Box::new(#value))
+            });
+            let validation = validator.map(|ValidateAttrs { with, .. }| {
+                quote! {
+                    if #with(&payload).is_err() {
+                        return ::core::result::Result::Err(::std::boxed::Box::new(payload));
+                    }
+                }
+            });
+            from_arms.push(quote! {
+                #kind_name::#kind_variant => {
+                    let payload = *payload.downcast::<#payload>()?;
+                    #validation
+                    let #value = payload;
+                    ::core::result::Result::Ok(#pattern)
+                }
+            });
+        }
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #into_doc]
+                #must_use
+                #vis fn into_parts(self) -> (#kind_name, ::std::boxed::Box<dyn ::core::any::Any>) {
+                    match self {
+                        #(#into_arms),*
+                    }
+                }
+
+                #[doc = #from_doc]
+                #must_use
+                #vis fn from_parts(
+                    kind: #kind_name,
+                    payload: ::std::boxed::Box<dyn ::core::any::Any>,
+                ) -> ::core::result::Result<Self, ::std::boxed::Box<dyn ::core::any::Any>> {
+                    match kind {
+                        #(#from_arms),*
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate `<variant>_field_<i>()` accessors and a `from_<variant>(...)` constructor for
+    /// each multi-field variant
+    pub fn field_accessors(&self, variants: &[&syn::Variant]) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let must_use = self.must_use();
+
+        let methods = variants
+            .iter()
+            .filter(|variant| variant.fields.len() > 1)
+            .map(|variant| {
+                let variant_name = &variant.ident;
+                let snake = crate::snake_case(&crate::kind_ident(variant));
+                let bindings = crate::field_bindings(&variant.fields);
+                let types: Vec<_> = variant.fields.iter().map(|f| &f.ty).collect();
+                let constructor = format_ident!("from_{}", snake);
+                let constructor_doc =
+                    format!("Build a [`{}::{}`] from its fields", name, variant_name);
+                let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+                let deprecated = crate::deprecated_attrs(variant);
+
+                let accessors = bindings.iter().zip(&variant.fields).enumerate().map(
+                    |(i, (binding, field))| {
+                        let field_type = &field.ty;
+                        // Named fields are accessed by name, tuple fields by position
+                        let (accessor, doc) = match &field.ident {
+                            Some(field_name) => (
+                                format_ident!("{}_{}", snake, field_name.unraw()),
+                                format!(
+                                    "Get the `{}` field if the enum holds a [`{}::{}`]",
+                                    field_name.unraw(),
+                                    name,
+                                    variant_name
+                                ),
+                            ),
+                            None => (
+                                format_ident!("{}_field_{}", snake, i),
+                                format!(
+                                    "Get field {} if the enum holds a [`{}::{}`]",
+                                    i, name, variant_name
+                                ),
+                            ),
+                        };
+                        quote! {
+                            #[doc = #doc]
+                            #(#deprecated)*
+                            #must_use
+                            #[allow(unused_variables)]
+                            #vis fn #accessor(&self) -> ::core::option::Option<&#field_type> {
+                                match self {
+                                    #pattern => ::core::option::Option::Some(#binding),
+                                    _ => ::core::option::Option::None,
+                                }
+                            }
+                        }
+                    },
+                );
+
+                quote! {
+                    #[doc = #constructor_doc]
+                    #(#deprecated)*
+                    #must_use
+                    #vis const fn #constructor(#(#bindings: #types),*) -> Self {
+                        #pattern
+                    }
+
+                    #(#accessors)*
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if methods.is_empty() {
+            return quote! {};
+        }
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#methods)*
+            }
+        }
+    }
+
+    /// Generate a `const fn from_<type>(value)` constructor for each of `constructible` holding a
+    /// single primitive like `i64` or `bool`, for building values in `const` and `static` items
+    ///
+    /// `constructible` are the variants keeping their `From` impl, since the constructor is as
+    /// unchecked as it is.
+    pub fn const_constructors(
+        &self,
+        variants: &[&syn::Variant],
+        constructible: &[&syn::Variant],
+    ) -> syn::Result<TokenStream> {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let must_use = self.must_use();
+        // `from_<variant>()` of multi-field variants is generated by `field_accessors()`
+        let taken: Vec<String> = variants
+            .iter()
+            .filter(|variant| variant.fields.len() > 1)
+            .map(|variant| format!("from_{}", crate::snake_case(&crate::kind_ident(variant))))
+            .collect();
+
+        let mut methods = Vec::new();
+        for variant in constructible {
+            let Some(Type::Path(path)) = crate::boxing::single_type(variant) else {
+                continue;
+            };
+            let Some(primitive) = path.path.get_ident().filter(|ident| {
+                PRIMITIVE_REPRS.iter().any(|repr| *ident == repr)
+                    || ["f32", "f64", "bool", "char"]
+                        .iter()
+                        .any(|other| *ident == other)
+            }) else {
+                continue;
+            };
+            let method = format_ident!("from_{}", primitive);
+            if taken.contains(&method.to_string()) {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "the constructor `{}` of `{}` would be named like the one of a \
+                        multi-field variant",
+                        method, variant.ident
+                    ),
+                ));
+            }
+            let doc = format!(
+                "Build a [`{}::{}`] in `const` contexts, like `From<{}>`",
+                name, variant.ident, primitive
+            );
+            let constructor = crate::variant_with_fields(&ty, variant, &[quote!(value)]);
+            let deprecated = crate::deprecated_attrs(variant);
+            methods.push(quote! {
+                #[doc = #doc]
+                #(#deprecated)*
+                #must_use
+                #vis const fn #method(value: #primitive) -> Self {
+                    #constructor
+                }
+            });
+        }
+
+        if methods.is_empty() {
+            return Ok(quote! {});
+        }
+        Ok(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#methods)*
+            }
+        })
+    }
+
+    /// Generate a `new_<variant>(value)` constructor for each variant holding a single
+    /// `Box<T>`, `Arc<T>` or `Rc<T>`, doing the wrapping
+    ///
+    /// Unsized pointees like `dyn Error` or `str` can't be passed by value and get none.
+    pub fn wrapping_constructors(&self, variants: &[&syn::Variant]) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let must_use = self.must_use();
+
+        let methods: Vec<TokenStream> = variants
+            .iter()
+            .filter_map(|variant| {
+                let pointer = crate::boxing::single_type(variant)?;
+                let (wrapper, inner) = ["Box", "Arc", "Rc"].iter().find_map(|wrapper| {
+                    Some((wrapper, crate::boxing::pointee(&pointer, wrapper)?))
+                })?;
+                let unsized_path = matches!(inner, Type::Path(path)
+                    if ["str", "Path", "OsStr", "CStr"].iter().any(|name| path.path.is_ident(name)));
+                if unsized_path || matches!(inner, Type::TraitObject(_) | Type::Slice(_)) {
+                    return None;
+                }
+                let variant_name = &variant.ident;
+                let method = format_ident!("new_{}", crate::snake_case(&crate::kind_ident(variant)));
+                let doc = format!(
+                    "Build a [`{}::{}`], wrapping `value` in {} `{}`",
+                    name,
+                    variant_name,
+                    if *wrapper == "Box" { "a" } else { "an" },
+                    wrapper
+                );
+                let constructor =
+                    crate::variant_with_fields(&ty, variant, &[quote!(<#pointer>::new(value))]);
+                let deprecated = crate::deprecated_attrs(variant);
+                Some(quote! {
+                    #[doc = #doc]
+                    #(#deprecated)*
+                    #must_use
+                    #vis fn #method(value: #inner) -> Self {
+                        #constructor
+                    }
+                })
+            })
+            .collect();
+
+        if methods.is_empty() {
+            return quote! {};
+        }
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#methods)*
+            }
+        }
+    }
+
+    /// Generate an `as_<variant>()` getter for each variant with fields, skipped ones included
+    ///
+    /// Multi-field variants return their fields as a tuple of references, like `Value` does.
+    pub fn getters(&self, ref_enum: bool, mut_enum: bool) -> syn::Result<TokenStream> {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let must_use = self.must_use();
+
+        let mut methods = Vec::new();
+        for variant in self
+            .variants
+            .iter()
+            .filter(|variant| !variant.fields.is_empty())
+        {
+            let variant_name = &variant.ident;
+            let snake = crate::snake_case(&crate::kind_ident(variant));
+            let clash = match snake.as_str() {
+                "ref" if ref_enum => Some("ref"),
+                "mut" if mut_enum => Some("mut"),
+                _ => None,
+            };
+            if let Some(option) = clash {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "the getter of `{}` would be named like the `as_{}()` of \
+                        #[type_enum({}_enum)]",
+                        variant_name, option, option
+                    ),
+                ));
+            }
+            let getter = format_ident!("as_{}", snake);
+            let doc = format!(
+                "Get the payload if the enum holds a [`{}::{}`]",
+                name, variant_name
+            );
+            let bindings = crate::field_bindings(&variant.fields);
+            let types: Vec<_> = variant.fields.iter().map(|f| &f.ty).collect();
+            let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+            let deprecated = crate::deprecated_attrs(variant);
+            let (output, value) = match &types[..] {
+                [field_type] => (quote!(&#field_type), quote!(#(#bindings)*)),
+                _ => (quote!((#(&#types),*)), quote!((#(#bindings),*))),
+            };
+            methods.push(quote! {
+                #[doc = #doc]
+                #(#deprecated)*
+                #must_use
+                #vis fn #getter(&self) -> ::core::option::Option<#output> {
+                    match self {
+                        #pattern => ::core::option::Option::Some(#value),
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            });
+        }
+
+        Ok(quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#methods)*
+            }
+        })
+    }
+
+    /// Generate an `is_<variant>()` predicate for each variant, and `holds::<T>()` checking for a
+    /// payload type
+    pub fn is_methods(&self, krate: &TokenStream) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let must_use = self.must_use();
+
+        let predicates = self.variants.iter().map(|variant| {
+            let variant_name = &variant.ident;
+            let predicate = format_ident!("is_{}", crate::snake_case(&crate::kind_ident(variant)));
+            let doc = format!("Whether the enum holds a [`{}::{}`]", name, variant_name);
+            let deprecated = crate::deprecated_attrs(variant);
+            quote! {
+                #[doc = #doc]
+                #(#deprecated)*
+                #must_use
+                #vis const fn #predicate(&self) -> bool {
+                    matches!(self, #name::#variant_name { .. })
+                }
+            }
+        });
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#predicates)*
+
+                /// Whether the enum holds a payload of type `T`
+                #must_use
+                #vis fn holds<'a, T: 'a>(&'a self) -> bool
+                where
+                    Self: #krate::Value<'a, &'a T>,
+                {
+                    #krate::Value::<&T>::value(self).is_some()
+                }
+            }
+        }
+    }
+
+    /// Generate `downcast_value::<T>()` for enums with variants holding a `Box<dyn Any>` or
+    /// `Box<dyn Error>`, or a `Box<dyn Trait>` in `opted_in`
+    ///
+    /// `dyn Any` and `dyn Error` have their own `downcast_ref`; the trait objects of variants
+    /// marked `#[type_enum(downcast)]` are upcast to `dyn Any`, which requires `Any` to be a
+    /// supertrait of the trait, so other trait objects like `dyn Display` are left out. The one
+    /// for `dyn Error` only downcasts to errors, so with such a variant `T` has to implement
+    /// `Error` as well.
+    pub fn downcast(&self, opted_in: &[&syn::Variant]) -> TokenStream {
+        let name = &self.input.ident;
+        let (impl_generics, ty_generics, where_clause) = self.input.generics.split_for_impl();
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let must_use = self.must_use();
+
+        let mut holds_error = false;
+        let arms: Vec<TokenStream> = self
+            .variants
+            .iter()
+            .filter_map(|variant| {
+                let [field] = variant.fields.iter().collect::<Vec<_>>()[..] else {
+                    return None;
+                };
+                let Some(Type::TraitObject(object)) = crate::boxing::boxed_type(&field.ty) else {
+                    return None;
+                };
+                let has_bound = |trait_name: &str| {
+                    object.bounds.iter().any(|bound| {
+                        matches!(bound, TypeParamBound::Trait(bound)
+                        if bound.path.segments.last().is_some_and(|segment| {
+                            segment.ident == trait_name
+                        }))
+                    })
+                };
+                let has_downcast = has_bound("Any") || has_bound("Error");
+                if !has_downcast && !opted_in.iter().any(|opted| opted.ident == variant.ident) {
+                    return None;
+                }
+                holds_error |= has_bound("Error");
+                let pattern = crate::variant_with_fields(&ty, variant, &[quote!(val)]);
+                Some(if has_downcast {
+                    quote! { #pattern => (**val).downcast_ref::<T>() }
+                } else {
+                    quote! {
+                        #pattern => {
+                            let any: &dyn ::core::any::Any = &**val;
+                            any.downcast_ref::<T>()
+                        }
+                    }
+                })
+            })
+            .collect();
+        if arms.is_empty() {
+            return quote! {};
+        }
+        let bounds = if holds_error {
+            quote!(::core::any::Any + ::core::error::Error)
+        } else {
+            quote!(::core::any::Any)
+        };
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Get the payload as a `T` if the enum holds a boxed trait object whose concrete
+                /// type is `T`, like the error in a `Box<dyn Error>`
+                #must_use
+                #vis fn downcast_value<T: #bounds>(&self) -> ::core::option::Option<&T> {
+                    match self {
+                        #(#arms,)*
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Generate the `<Name>Visitor` trait, with one method per variant, and the `accept()`
+    /// method calling the one matching the held variant
+    pub fn visitor(&self) -> TokenStream {
+        self.visitor_trait(false)
+    }
+
+    /// Generate `from_any()`, building the variant whose payload type is `T`, or `catch_all`
+    /// holding the boxed value if there is none
+    ///
+    /// `variants` are the ones keeping their `From` impl, whose payloads are compared by `TypeId`,
+    /// so they must be `'static`. Values of `no_from` and validated variants go to `catch_all`.
+    pub fn catch_all(
+        &self,
+        catch_all: &syn::Variant,
+        variants: &[&syn::Variant],
+    ) -> syn::Result<TokenStream> {
+        let name = &self.input.ident;
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let object = crate::boxing::single_type(catch_all)
+            .as_ref()
+            .and_then(|ty| match crate::boxing::boxed_type(ty) {
+                Some(Type::TraitObject(object)) => Some(object.clone()),
+                _ => None,
+            })
+            .filter(|object| {
+                object.bounds.iter().any(|bound| {
+                    matches!(bound, TypeParamBound::Trait(bound)
+                        if bound.path.segments.last().is_some_and(|s| s.ident == "Any"))
+                })
+            });
+        let Some(object) = object else {
+            return Err(syn::Error::new_spanned(
+                catch_all,
+                format!(
+                    "`#[type_enum(catch_all)]` needs `{}` to hold a single `Box<dyn Any>`, \
+                    optionally `+ Send` or `+ Sync`",
+                    catch_all.ident
+                ),
+            ));
+        };
+        if !self.input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &self.input.generics,
+                format!(
+                    "`#[type_enum(catch_all)]` is not supported on generic enums like `{}`",
+                    name
+                ),
+            ));
+        }
+        let bounds = &object.bounds;
+        let doc = format!(
+            "Build the variant of [`{}`] holding a `T`, or [`{}::{}`] with the boxed value if no \
+            variant does",
+            name, name, catch_all.ident
+        );
+
+        let attempts = variants
+            .iter()
+            .filter(|variant| variant.ident != catch_all.ident)
+            .map(|variant| {
+                let payload = crate::payload_type(&variant.fields);
+                let bindings = crate::field_bindings(&variant.fields);
+                let constructor = crate::variant_with_fields(&ty, variant, &bindings);
+                let destructure = match &bindings[..] {
+                    [binding] => quote!(#binding),
+                    _ => quote!((#(#bindings),*)),
+                };
+                quote! {
+                    if let Some(slot) = (&mut value as &mut dyn ::core::any::Any)
+                        .downcast_mut::<Option<#payload>>()
+                    {
+                        let #destructure = slot.take().expect("the value is taken once");
+                        return #constructor;
+                    }
+                }
+            });
+        let fallback =
+            crate::variant_with_fields(&ty, catch_all, &[quote!(::std::boxed::Box::new(value))]);
+
+        Ok(quote! {
+            impl #name {
+                #[doc = #doc]
+                #vis fn from_any<T: #bounds>(value: T) -> Self {
+                    let mut value = Some(value);
+                    #(#attempts)*
+                    let value = value.expect("no variant took the value");
+                    #fallback
+                }
+            }
+        })
+    }
+
+    /// Generate the `<Name>AsyncVisitor` trait, with one method per variant, and the
+    /// `accept_async()` method calling the one matching the held variant
+    pub fn async_visitor(&self) -> TokenStream {
+        self.visitor_trait(true)
+    }
+
+    /// Generate the `<Name>Handler` trait, with one method per variant taking its fields by
+    /// value, and the `handle()` method consuming the enum into the matching one
+    pub fn handler(&self) -> TokenStream {
+        let name = &self.input.ident;
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let handler_name = format_ident!("{}Handler", name);
+        let trait_doc = format!(
+            "Handler for the variants of [`{}`], called by [`{}::handle`]",
+            name, name
+        );
+        let handle_doc = format!(
+            "Pass the fields of the held variant to its [`{}`] method",
+            handler_name
+        );
+
+        let mut methods = Vec::new();
+        let mut arms = Vec::new();
+        for variant in &self.variants {
+            let method = format_ident!("on_{}", crate::snake_case(&crate::kind_ident(variant)));
+            let bindings = crate::field_bindings(&variant.fields);
+            let types = variant.fields.iter().map(|f| &f.ty);
+            let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+            let deprecated = crate::deprecated_attrs(variant);
+            methods.push(quote! {
+                #(#deprecated)*
+                fn #method(&mut self, #(#bindings: #types),*) -> Self::Output;
+            });
+            arms.push(quote! {
+                #pattern => handler.#method(#(#bindings),*)
+            });
+        }
+
+        quote! {
+            #[doc = #trait_doc]
+            #vis trait #handler_name {
+                /// The result of handling a value
+                type Output;
+
+                #(#methods)*
+            }
+
+            impl #name {
+                #[doc = #handle_doc]
+                #vis fn handle<H: #handler_name>(self, handler: &mut H) -> H::Output {
+                    match self {
+                        #(#arms),*
+                    }
+                }
+            }
+        }
+    }
+
+    fn visitor_trait(&self, is_async: bool) -> TokenStream {
+        let name = &self.input.ident;
+        let vis = &self.vis;
+        let ty = quote!(#name);
+        let (visitor_name, accept, sync_word) = if is_async {
+            (
+                format_ident!("{}AsyncVisitor", name),
+                format_ident!("accept_async"),
+                "Async visitor",
+            )
+        } else {
+            (
+                format_ident!("{}Visitor", name),
+                format_ident!("accept"),
+                "Visitor",
+            )
+        };
+        let trait_doc = format!(
+            "{} over the variants of [`{}`], called by [`{}::{}`]",
+            sync_word, name, name, accept
+        );
+        let accept_doc = if is_async {
+            format!("Await the [`{}`] method for the held variant", visitor_name)
+        } else {
+            format!("Call the [`{}`] method for the held variant", visitor_name)
+        };
+        let (output, asyncness, awaiting) = if is_async {
+            (
+                quote!(impl ::core::future::Future<Output = Self::Output>),
+                quote!(async),
+                quote!(.await),
+            )
+        } else {
+            (quote!(Self::Output), quote!(), quote!())
+        };
+
+        let mut methods = Vec::new();
+        let mut arms = Vec::new();
+        for variant in &self.variants {
+            let method = format_ident!("visit_{}", crate::snake_case(&crate::kind_ident(variant)));
+            let bindings = crate::field_bindings(&variant.fields);
+            let types = variant.fields.iter().map(|f| &f.ty);
+            let pattern = crate::variant_with_fields(&ty, variant, &bindings);
+            let deprecated = crate::deprecated_attrs(variant);
+            methods.push(quote! {
+                #(#deprecated)*
+                fn #method(
+                    &mut self,
+                    #(#bindings: &#types),*
+                ) -> #output;
+            });
+            arms.push(quote! {
+                #pattern => visitor.#method(#(#bindings),*)#awaiting
+            });
+        }
+
+        quote! {
+            #[doc = #trait_doc]
+            #[allow(clippy::ptr_arg)]
+            #vis trait #visitor_name {
+                /// The result of visiting a value
+                type Output;
+
+                #(#methods)*
+            }
+
+            impl #name {
+                #[doc = #accept_doc]
+                #vis #asyncness fn #accept<V: #visitor_name>(&self, visitor: &mut V) -> V::Output {
+                    match self {
+                        #(#arms),*
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether `tokens` name `lifetime` anywhere, like `Vec<&'a str>` does `'a`
+fn mentions_lifetime(tokens: TokenStream, lifetime: &syn::Lifetime) -> bool {
+    let mut after_quote = false;
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Group(group) => {
+            after_quote = false;
+            mentions_lifetime(group.stream(), lifetime)
+        }
+        TokenTree::Punct(punct) => {
+            after_quote = punct.as_char() == '\'';
+            false
+        }
+        TokenTree::Ident(ident) => std::mem::take(&mut after_quote) && ident == lifetime.ident,
+        TokenTree::Literal(_) => {
+            after_quote = false;
+            false
+        }
+    })
+}
+
+/// Visibility for an item moved one module deeper that keeps it reachable from the same scope
+fn nested_vis(vis: &Visibility) -> Visibility {
+    match vis {
+        Visibility::Inherited => parse_quote!(pub(super)),
+        Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            if path.is_ident("self") {
+                parse_quote!(pub(super))
+            } else if path.segments.first().is_some_and(|s| s.ident == "crate") {
+                vis.clone()
+            } else if path.segments.first().is_some_and(|s| s.ident == "self") {
+                let rest = path.segments.iter().skip(1);
+                parse_quote!(pub(in super #(::#rest)*))
+            } else {
+                parse_quote!(pub(in super::#path))
+            }
+        }
+        Visibility::Public(_) => vis.clone(),
+    }
+}
@@ -0,0 +1,171 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Ident, Index, LitInt};
+
+use crate::{field_bindings, generics, payload_type, variant_with_fields};
+
+/// Generate the comparisons between the enum and the payload type `T` of each of `variants`, in
+/// both directions
+///
+/// `PartialEq<T>` is always generated, and `PartialOrd<T>` if `ord` is set. A value holding
+/// another variant is unequal to every `T`, and unordered relative to it.
+pub fn payload_cmp(name: &Ident, variants: &[&syn::Variant], ord: bool) -> TokenStream {
+    let ty = quote!(#name);
+    let impls = variants.iter().map(|variant| {
+        let payload = payload_type(&variant.fields);
+        let bindings = field_bindings(&variant.fields);
+        let pattern = variant_with_fields(&ty, variant, &bindings);
+        // Multi-field payloads compare as tuples of references to their fields
+        let (held, other, this) = if bindings.len() == 1 {
+            (quote!(field_0), quote!(other), quote!(self))
+        } else {
+            let indices: Vec<_> = (0..bindings.len()).map(Index::from).collect();
+            (
+                quote!((#(#bindings),*)),
+                quote!((#(&other.#indices),*)),
+                quote!((#(&self.#indices),*)),
+            )
+        };
+        let eq = quote! {
+            impl PartialEq<#payload> for #name {
+                fn eq(&self, other: &#payload) -> bool {
+                    match self {
+                        #pattern => PartialEq::eq(&#held, &#other),
+                        _ => false,
+                    }
+                }
+            }
+
+            impl PartialEq<#name> for #payload {
+                fn eq(&self, other: &#name) -> bool {
+                    match other {
+                        #pattern => PartialEq::eq(&#this, &#held),
+                        _ => false,
+                    }
+                }
+            }
+        };
+        if !ord {
+            return eq;
+        }
+        quote! {
+            #eq
+
+            impl PartialOrd<#payload> for #name {
+                fn partial_cmp(&self, other: &#payload) -> Option<::core::cmp::Ordering> {
+                    match self {
+                        #pattern => PartialOrd::partial_cmp(&#held, &#other),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl PartialOrd<#name> for #payload {
+                fn partial_cmp(&self, other: &#name) -> Option<::core::cmp::Ordering> {
+                    match other {
+                        #pattern => PartialOrd::partial_cmp(&#this, &#held),
+                        _ => None,
+                    }
+                }
+            }
+        }
+    });
+    quote! { #(#impls)* }
+}
+
+/// Generate `Hash`, `PartialOrd` and `Ord` for `#[type_enum(delegate_ord)]`, ordering values by
+/// the precedence of their variant first and by their payloads second
+///
+/// The precedence is each variant's `#[type_enum(tag = N)]` if the variants have tags, and their
+/// declaration order otherwise. `variants` are all of the enum's variants, with `tags` in the same
+/// order.
+pub fn delegate_ord(
+    input: &DeriveInput,
+    variants: &[&syn::Variant],
+    tags: &[Option<LitInt>],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let ty = quote!(#name);
+    let ranks: Vec<u64> = if tags.iter().all(Option::is_some) {
+        tags.iter()
+            .flatten()
+            .map(LitInt::base10_parse)
+            .collect::<syn::Result<_>>()?
+    } else {
+        (0..variants.len() as u64).collect()
+    };
+    let rank_arms = variants.iter().zip(&ranks).map(|(variant, rank)| {
+        let variant_name = &variant.ident;
+        quote!(#name::#variant_name { .. } => #rank)
+    });
+    let rank = quote! {
+        let rank = |value: &Self| -> u64 {
+            match value {
+                #(#rank_arms),*
+            }
+        };
+    };
+
+    let mut hash_arms = Vec::new();
+    let mut cmp_arms = Vec::new();
+    for variant in variants {
+        let bindings = field_bindings(&variant.fields);
+        let others: Vec<_> = bindings
+            .iter()
+            .map(|binding| quote::format_ident!("other_{}", binding))
+            .collect();
+        let pattern = variant_with_fields(&ty, variant, &bindings);
+        let other_pattern = variant_with_fields(&ty, variant, &others);
+        hash_arms.push(quote! {
+            #pattern => {
+                #(::core::hash::Hash::hash(#bindings, state);)*
+            }
+        });
+        cmp_arms.push(quote! {
+            (#pattern, #other_pattern) => {
+                ::core::cmp::Ord::cmp(&(#(#bindings,)*), &(#(#others,)*))
+            }
+        });
+    }
+
+    let payloads: Vec<_> = variants
+        .iter()
+        .flat_map(|variant| &variant.fields)
+        .map(|field| &field.ty)
+        .collect();
+    let hash_generics = generics::with_bounds(
+        &input.generics,
+        payloads.iter().copied(),
+        &quote!(::core::hash::Hash),
+    );
+    let (hash_impl_generics, ty_generics, hash_where_clause) = hash_generics.split_for_impl();
+    let ord_generics = generics::with_bounds(&input.generics, payloads, &quote!(::core::cmp::Ord));
+    let (ord_impl_generics, _, ord_where_clause) = ord_generics.split_for_impl();
+    Ok(quote! {
+        impl #hash_impl_generics ::core::hash::Hash for #name #ty_generics #hash_where_clause {
+            fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                #rank
+                ::core::hash::Hash::hash(&rank(self), state);
+                match self {
+                    #(#hash_arms)*
+                }
+            }
+        }
+
+        impl #ord_impl_generics ::core::cmp::PartialOrd for #name #ty_generics #ord_where_clause {
+            fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                Some(::core::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        impl #ord_impl_generics ::core::cmp::Ord for #name #ty_generics #ord_where_clause {
+            fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                #rank
+                match (self, other) {
+                    #(#cmp_arms)*
+                    _ => ::core::cmp::Ord::cmp(&rank(self), &rank(other)),
+                }
+            }
+        }
+    })
+}
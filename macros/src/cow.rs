@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, GenericArgument, PathArguments, Type};
+
+use crate::attrs::Families;
+use crate::boxing::single_type;
+use crate::{generics, normalize, variant_with_fields};
+
+/// The borrowed type `B` of a `Cow<'a, B>` field type, with its lifetime
+pub fn cow_type(ty: &Type) -> Option<(&syn::Lifetime, &Type)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Cow" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>()[..] {
+        [
+            GenericArgument::Lifetime(lifetime),
+            GenericArgument::Type(borrowed),
+        ] => Some((lifetime, borrowed)),
+        _ => None,
+    }
+}
+
+/// The owned counterpart of `borrowed`, spelled out when it is known so that it can be compared
+/// with the other payloads
+///
+/// Other types fall back to `<B as ToOwned>::Owned`.
+pub fn owned_type(borrowed: &Type) -> Type {
+    known_owned_type(borrowed)
+        .unwrap_or_else(|| syn::parse_quote!(<#borrowed as ::std::borrow::ToOwned>::Owned))
+}
+
+/// The owned counterpart of the std borrowed types and of slices
+pub fn known_owned_type(borrowed: &Type) -> Option<Type> {
+    let path = match borrowed {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            return Some(syn::parse_quote!(Vec<#elem>));
+        }
+        _ => return None,
+    };
+    let segments: Vec<_> = path.segments.iter().collect();
+    let (last, prefix) = segments.split_last()?;
+    let in_std = match prefix.first() {
+        None => path.leading_colon.is_none(),
+        Some(first) => ["std", "core", "alloc"]
+            .iter()
+            .any(|krate| first.ident == krate),
+    };
+    if !in_std || !last.arguments.is_empty() {
+        return None;
+    }
+    Some(match last.ident.to_string().as_str() {
+        "str" => syn::parse_quote!(String),
+        "Path" => syn::parse_quote!(::std::path::PathBuf),
+        "OsStr" => syn::parse_quote!(::std::ffi::OsString),
+        "CStr" => syn::parse_quote!(::std::ffi::CString),
+        _ => return None,
+    })
+}
+
+/// Generate the extra conversions of variants holding a `Cow<'a, B>`
+///
+/// Such a variant also converts from `&'a B` and from the owned type, gives access to the `&B`
+/// view, and extracts the owned type with `into_owned()`. A conversion is left out if another
+/// variant holds the type it would convert from or to, or if the owned type isn't known.
+pub fn cow_impls(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    variants: &[(&syn::Variant, Families)],
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let accessor = generics::accessor_lifetime(&input.generics);
+    let accessor_generics = generics::with_accessor_lifetime(&input.generics, &accessor);
+    let (accessor_impl_generics, _, _) = accessor_generics.split_for_impl();
+
+    let held: Vec<String> = variants
+        .iter()
+        .filter_map(|(variant, _)| single_type(variant))
+        .map(|ty| normalize::type_key(&ty))
+        .collect();
+    let is_free = |ty: &Type| !held.contains(&normalize::type_key(ty));
+
+    let mut items = Vec::new();
+    for &(variant, families) in variants {
+        let Some(field_type) = single_type(variant) else {
+            continue;
+        };
+        let Some((lifetime, borrowed)) = cow_type(&field_type) else {
+            continue;
+        };
+        // An owned type only known as `<B as ToOwned>::Owned` could be any of the other payloads
+        let owned = known_owned_type(borrowed);
+        let reference: Type = syn::parse_quote!(&#lifetime #borrowed);
+        let kind_variant = crate::kind_ident(variant);
+        let pattern = variant_with_fields(&quote!(#name), variant, &[quote!(val)]);
+        let cow = quote!(::std::borrow::Cow);
+
+        if families.from && is_free(&reference) {
+            let constructor =
+                variant_with_fields(&quote!(#name), variant, &[quote!(#cow::Borrowed(value))]);
+            items.push(quote! {
+                impl #impl_generics From<#reference> for #name #ty_generics #where_clause {
+                    fn from(value: #reference) -> Self {
+                        #constructor
+                    }
+                }
+
+                impl #impl_generics #krate::Payload<#name #ty_generics> for #reference #where_clause {
+                    const KIND: #kind_path = #kind_path::#kind_variant;
+                }
+            });
+        }
+        if let Some(owned) = owned
+            .as_ref()
+            .filter(|owned| families.from && is_free(owned))
+        {
+            let constructor =
+                variant_with_fields(&quote!(#name), variant, &[quote!(#cow::Owned(value))]);
+            items.push(quote! {
+                impl #impl_generics From<#owned> for #name #ty_generics #where_clause {
+                    fn from(value: #owned) -> Self {
+                        #constructor
+                    }
+                }
+
+                impl #impl_generics #krate::Payload<#name #ty_generics> for #owned #where_clause {
+                    const KIND: #kind_path = #kind_path::#kind_variant;
+                }
+            });
+        }
+        if let Some(owned) = owned
+            .as_ref()
+            .filter(|owned| families.into_value && is_free(owned))
+        {
+            items.push(quote! {
+                impl #impl_generics #krate::IntoValue<#owned> for #name #ty_generics #where_clause {
+                    fn into_value(self) -> Result<#owned, Self> {
+                        match self {
+                            #pattern => Ok(val.into_owned()),
+                            other => Err(other),
+                        }
+                    }
+                }
+            });
+        }
+        if families.value && is_free(borrowed) {
+            items.push(quote! {
+                impl #accessor_impl_generics #krate::Value<#accessor, &#accessor #borrowed> for #name #ty_generics #where_clause {
+                    fn value(&#accessor self) -> Option<&#accessor #borrowed> {
+                        match self {
+                            #pattern => Some(&**val),
+                            _ => None,
+                        }
+                    }
+                }
+            });
+        }
+    }
+    quote! { #(#items)* }
+}
@@ -0,0 +1,148 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, LitStr};
+
+use crate::generics;
+
+/// Generate `Display` forwarding to the held value, with unit variants shown by name
+///
+/// `formats` holds each variant's `#[type_enum(display = "...")]` string, in declaration order.
+/// A variant with one is written with that format instead, referring to tuple fields as `{0}`
+/// and to named fields by name.
+pub fn display_impl(
+    input: &DeriveInput,
+    variants: &[&syn::Variant],
+    formats: &[Option<LitStr>],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let mut arms = Vec::new();
+    let mut payloads = Vec::new();
+    for (variant, format) in variants.iter().zip(formats) {
+        let variant_name = &variant.ident;
+        if let Some(format) = format {
+            let bindings: Vec<_> = variant
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| match &field.ident {
+                    Some(field_name) => field_name.clone(),
+                    None => format_ident!("_{}", i),
+                })
+                .collect();
+            let pattern = crate::variant_with_fields(&quote!(#name), variant, &bindings);
+            let format = LitStr::new(&positional_to_named(&format.value()), format.span());
+            arms.push(quote!(#pattern => write!(f, #format)));
+            continue;
+        }
+        match variant.fields.iter().collect::<Vec<_>>()[..] {
+            [] => {
+                let name_str = variant_name.to_string();
+                arms.push(quote!(#name::#variant_name { .. } => f.pad(#name_str)));
+            }
+            [field] => {
+                let pattern = crate::variant_with_fields(&quote!(#name), variant, &[quote!(val)]);
+                arms.push(quote!(#pattern => ::core::fmt::Display::fmt(val, f)));
+                payloads.push(&field.ty);
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "`{}` has several fields to display; give it a format with \
+                        #[type_enum(display = \"...\")]",
+                        variant_name
+                    ),
+                ));
+            }
+        }
+    }
+
+    let generics = generics::with_bounds(&input.generics, payloads, &quote!(::core::fmt::Display));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            #[allow(unused_variables, non_shorthand_field_patterns)]
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
+    })
+}
+
+/// Rewrite the positional arguments of a format string, `{0}` or `{}`, to the `_0` bindings of
+/// tuple fields, so that `write!` captures them
+fn positional_to_named(format: &str) -> String {
+    let mut output = String::with_capacity(format.len());
+    let mut next_implicit = 0;
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        output.push(c);
+        if c != '{' {
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            output.push(chars.next().unwrap());
+            continue;
+        }
+        let mut index = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            index.push(digit);
+        }
+        match chars.peek() {
+            Some('}' | ':') if index.is_empty() => {
+                output.push_str(&format!("_{}", next_implicit));
+                next_implicit += 1;
+            }
+            _ if !index.is_empty() => output.push_str(&format!("_{}", index)),
+            _ => {}
+        }
+    }
+    output
+}
+
+/// Generate `Error` forwarding `source()` and `description()` to the held value, for enums
+/// whose payloads are all errors
+///
+/// Variants without a single field have no source and are described by their name.
+pub fn error_impl(input: &DeriveInput, variants: &[&syn::Variant]) -> TokenStream {
+    let name = &input.ident;
+    let mut source_arms = Vec::new();
+    let mut description_arms = Vec::new();
+    let mut payloads = Vec::new();
+    for variant in variants {
+        let variant_name = &variant.ident;
+        match variant.fields.iter().collect::<Vec<_>>()[..] {
+            [field] => {
+                let pattern = crate::variant_with_fields(&quote!(#name), variant, &[quote!(val)]);
+                source_arms.push(quote!(#pattern => ::std::error::Error::source(val)));
+                description_arms.push(quote!(#pattern => ::std::error::Error::description(val)));
+                payloads.push(&field.ty);
+            }
+            _ => {
+                let name_str = variant_name.to_string();
+                source_arms.push(quote!(#name::#variant_name { .. } => None));
+                description_arms.push(quote!(#name::#variant_name { .. } => #name_str));
+            }
+        }
+    }
+
+    let generics = generics::with_bounds(&input.generics, payloads, &quote!(::std::error::Error));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::std::error::Error for #name #ty_generics #where_clause {
+            fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+                match self {
+                    #(#source_arms),*
+                }
+            }
+
+            fn description(&self) -> &str {
+                match self {
+                    #(#description_arms),*
+                }
+            }
+        }
+    }
+}
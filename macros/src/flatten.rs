@@ -0,0 +1,128 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{DeriveInput, Ident, Type};
+
+use crate::attrs::Families;
+use crate::boxing::single_type;
+
+/// Name of the hidden macro generating the `From` impls of an enum flattening `name`
+fn macro_name(name: &Ident) -> Ident {
+    format_ident!("__type_enum_flatten_{}", name)
+}
+
+/// Name of the hidden alias of the `index`th payload type of `name`, for the flattening macro
+fn alias_name(name: &Ident, index: usize) -> Ident {
+    format_ident!("__TypeEnumFlatten_{}_{}", name, index)
+}
+
+/// Generate the hidden macro other enums invoke with `#[type_enum(flatten)]`
+///
+/// `__type_enum_flatten_Name!(Outer, Name; path::)` implements `From` on `Outer` for each payload
+/// type `Name` converts from. The types are named through hidden aliases next to `Name`, reached
+/// with the `path::` the outer enum wrote `Name` with, since the paths written in `Name` may not
+/// resolve where `Outer` is defined. Projections like `<Types as At<I>>::Output` would resolve,
+/// but rustc's coherence check doesn't normalize them outside this crate. Generic enums get no macro.
+pub fn flatten_macro(input: &DeriveInput, variants: &[(&syn::Variant, Families)]) -> TokenStream {
+    if !input.generics.params.is_empty() {
+        return quote! {};
+    }
+    let name = &input.ident;
+    let vis = &input.vis;
+    let macro_name = macro_name(name);
+    let mut aliases = Vec::new();
+    let mut elements = Vec::new();
+    for (index, &(variant, families)) in variants.iter().enumerate() {
+        if families.from {
+            let alias = alias_name(name, index);
+            let payload = crate::payload_type(&variant.fields);
+            aliases.push(quote! {
+                #[doc(hidden)]
+                #[allow(non_camel_case_types)]
+                #vis type #alias = #payload;
+            });
+            elements.push(quote!($($path)* #alias));
+        }
+    }
+    quote! {
+        #(#aliases)*
+
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #macro_name {
+            ($outer:ty, $inner:ty; $($path:tt)*) => {
+                #(
+                    #[automatically_derived]
+                    impl ::core::convert::From<#elements> for $outer {
+                        fn from(value: #elements) -> Self {
+                            <$outer as ::core::convert::From<$inner>>::from(
+                                <$inner as ::core::convert::From<#elements>>::from(value),
+                            )
+                        }
+                    }
+                )*
+            };
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #macro_name;
+    }
+}
+
+/// Invoke the hidden macro of each enum held by a variant marked `#[type_enum(flatten)]`
+///
+/// The macro is found next to the held enum, by the path its type is written with.
+pub fn flatten_impls(
+    input: &DeriveInput,
+    flattened: &[(&syn::Variant, Families)],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let mut items = Vec::new();
+    for &(variant, families) in flattened {
+        if !input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input.generics,
+                format!(
+                    "`#[type_enum(flatten)]` is not supported on generic enums like `{}`",
+                    name
+                ),
+            ));
+        }
+        let field_type = single_type(variant);
+        let Some(Type::Path(path)) = field_type.as_ref().filter(|ty| {
+            matches!(ty, Type::Path(path) if path.qself.is_none()
+                && path.path.segments.last().is_some_and(|last| last.arguments.is_none()))
+        }) else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(flatten)]` needs `{}` to hold a single field of a non-generic \
+                    enum deriving TypeEnum",
+                    variant.ident
+                ),
+            ));
+        };
+        if !families.from {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(flatten)]` converts through the `From` impl of `{}`, which is \
+                    left out",
+                    variant.ident
+                ),
+            ));
+        }
+        let mut macro_path = path.path.clone();
+        let last = macro_path.segments.last_mut().expect("checked above");
+        last.ident = macro_name(&last.ident);
+        // The module path in front of the enum's name, like `json::`, where its aliases are
+        let mut module = path.path.clone();
+        module.segments.pop();
+        let field_type = &path.path;
+        items.push(quote_spanned! {field_type.span()=>
+            #macro_path!(#name, #field_type; #module);
+        });
+    }
+    Ok(quote! { #(#items)* })
+}
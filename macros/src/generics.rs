@@ -0,0 +1,240 @@
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::{ToTokens, quote};
+use syn::{DeriveInput, Generics, Ident, Lifetime, Type, parse_quote};
+
+use crate::attrs::ContainerAttrs;
+
+/// A copy of the enum with `Self` in field types replaced by the enum's type, like
+/// `Box<Self>` by `Box<Expr>`
+///
+/// Generated impls would read `Self` as their own self type, or reject it in `From<Box<Self>>`.
+pub fn resolve_self(input: &DeriveInput) -> DeriveInput {
+    let mut input = input.clone();
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let self_type = quote!(#name #ty_generics);
+    let syn::Data::Enum(data) = &mut input.data else {
+        return input;
+    };
+    for field in data
+        .variants
+        .iter_mut()
+        .flat_map(|variant| &mut variant.fields)
+    {
+        let tokens = replace_self(field.ty.to_token_stream(), &self_type);
+        field.ty = syn::parse2(tokens).expect("replacing `Self` keeps the type valid");
+    }
+    input
+}
+
+fn replace_self(tokens: TokenStream, self_type: &TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .flat_map(|tree| match tree {
+            TokenTree::Ident(ident) if ident == "Self" => self_type.clone(),
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    Group::new(group.delimiter(), replace_self(group.stream(), self_type));
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced).into()
+            }
+            tree => tree.into(),
+        })
+        .collect()
+}
+
+/// The lifetime of the borrow in the `Value` and `ValueMut` impls: `'a`, or the first of `'b`,
+/// `'c`, ... the enum doesn't declare itself
+pub fn accessor_lifetime(generics: &Generics) -> Lifetime {
+    let declared: Vec<String> = generics
+        .lifetimes()
+        .map(|param| param.lifetime.ident.to_string())
+        .collect();
+    let name = ('a'..='z')
+        .map(String::from)
+        .chain((0..).map(|i| format!("a{}", i)))
+        .find(|name| !declared.contains(name))
+        .expect("an unused lifetime name exists");
+    Lifetime::new(&format!("'{}", name), Span::call_site())
+}
+
+/// The enum's generics with the accessor lifetime added in front
+///
+/// Borrowed payloads like `&'e str` appear in the impl header as `&'a &'e str`, which already
+/// implies `'e: 'a`.
+pub fn with_accessor_lifetime(generics: &Generics, lifetime: &Lifetime) -> Generics {
+    let mut generics = generics.clone();
+    generics.params.insert(0, parse_quote!(#lifetime));
+    generics
+}
+
+/// The enum's generics with `bound` required of each of `types`, for impls like `Default` that
+/// need it of the payloads
+///
+/// Non-generic enums get no predicates, leaving a missing impl to the generated code.
+pub fn with_bounds<'t>(
+    generics: &Generics,
+    types: impl IntoIterator<Item = &'t Type>,
+    bound: &TokenStream,
+) -> Generics {
+    let mut generics = generics.clone();
+    if !generics.params.is_empty() {
+        let where_clause = generics.make_where_clause();
+        for ty in types {
+            where_clause.predicates.push(parse_quote!(#ty: #bound));
+        }
+    }
+    generics
+}
+
+/// Reject the options whose generated code doesn't carry the enum's generic parameters
+pub fn check_supported(
+    name: &Ident,
+    generics: &Generics,
+    attrs: &ContainerAttrs,
+) -> syn::Result<()> {
+    if generics.params.is_empty() {
+        return Ok(());
+    }
+    let unsupported = [
+        ("test_roundtrip", attrs.test_roundtrip),
+        ("ref_enum", attrs.ref_enum),
+        ("mut_enum", attrs.mut_enum),
+        ("delegate_ops", !attrs.delegate_ops.is_empty()),
+        ("visitor", attrs.visitor),
+        ("async_visitor", attrs.async_visitor),
+        ("handler", attrs.handler),
+        ("partition", attrs.partition),
+        ("bson", attrs.bson.is_some()),
+        ("otel", attrs.otel),
+        ("transitions", !attrs.transitions.is_empty()),
+        ("subset_of", !attrs.subset_of.is_empty()),
+        ("actix", attrs.actix.is_some()),
+        ("auto_box_over", attrs.auto_box_over.is_some()),
+        ("serde", attrs.serde.is_some()),
+        ("redis", attrs.redis),
+        ("ts", attrs.ts),
+        ("telemetry", attrs.telemetry),
+        ("payload_eq", attrs.payload_eq),
+        ("payload_cmp", attrs.payload_cmp),
+    ];
+    match unsupported.iter().find(|(_, enabled)| *enabled) {
+        Some((option, _)) => Err(syn::Error::new_spanned(
+            generics,
+            format!(
+                "`#[type_enum({})]` is not supported on generic enums like `{}`",
+                option, name
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Reject payloads whose impls would overlap for some choice of the enum's generic parameters
+///
+/// `impl<T> From<T> for Wrapper<T>` overlaps with the `From` impl of every other payload, since
+/// `T` can be any of those types, and `[u8; N]` overlaps with `[u8; 4]`. rustc would reject the
+/// generated impls with an error pointing at the derive.
+pub fn check_overlap(generics: &Generics, variants: &[&syn::Variant]) -> syn::Result<()> {
+    if variants.len() < 2 {
+        return Ok(());
+    }
+    for variant in variants {
+        let [field] = variant.fields.iter().collect::<Vec<_>>()[..] else {
+            continue;
+        };
+        let Type::Path(path) = &field.ty else {
+            continue;
+        };
+        if let Some(param) = generics
+            .type_params()
+            .find(|param| path.qself.is_none() && path.path.is_ident(&param.ident))
+        {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                format!(
+                    "`{}` holds the type parameter `{}`, which could be the payload type of \
+                    another variant; wrap it in a type of its own or mark the variant \
+                    #[type_enum(skip)]",
+                    variant.ident, param.ident
+                ),
+            ));
+        }
+    }
+
+    let const_params: Vec<&Ident> = generics.const_params().map(|param| &param.ident).collect();
+    if const_params.is_empty() {
+        return Ok(());
+    }
+    let payloads: Vec<TokenStream> = variants
+        .iter()
+        .map(|variant| crate::payload_type(&variant.fields))
+        .collect();
+    for (i, first) in variants.iter().enumerate() {
+        for (j, second) in variants.iter().enumerate().skip(i + 1) {
+            if may_match(payloads[i].clone(), payloads[j].clone(), &const_params) {
+                return Err(syn::Error::new_spanned(
+                    second,
+                    format!(
+                        "duplicate type in enum: variants `{}` and `{}` hold the same type(s) for \
+                        some values of the const parameters. Each variant must hold a unique \
+                        type. Use #[type_enum(skip)] to exclude a variant.",
+                        first.ident, second.ident
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a payload gets `TryFrom<Enum>`, and its reference `TryFrom<&Enum>`
+///
+/// Payloads naming a type parameter or the enum itself are left out: `impl<T> TryFrom<Wrapper<T>>
+/// for T` breaks the orphan rules, and `Box<Expr>` already converts from `Expr` through std's
+/// blanket impl. So are boxed trait objects, which an error enum implementing `Error` converts
+/// into through std's `From<E: Error> for Box<dyn Error>`.
+pub fn converts_from_enum(input: &DeriveInput, payload: TokenStream) -> bool {
+    if let Ok(payload) = syn::parse2::<Type>(payload.clone())
+        && matches!(
+            crate::boxing::boxed_type(&payload),
+            Some(Type::TraitObject(_))
+        )
+    {
+        return false;
+    }
+    let mut names: Vec<&Ident> = input
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect();
+    names.push(&input.ident);
+    !names_any(payload, &names)
+}
+
+fn names_any(tokens: TokenStream, names: &[&Ident]) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Group(group) => names_any(group.stream(), names),
+        TokenTree::Ident(ident) => names.contains(&&ident),
+        _ => false,
+    })
+}
+
+/// Whether two types are written the same, with each const parameter standing in for any
+/// single-token const argument, like `N` for the `4` in `[u8; 4]`
+fn may_match(first: TokenStream, second: TokenStream, const_params: &[&Ident]) -> bool {
+    let is_param =
+        |tree: &TokenTree| matches!(tree, TokenTree::Ident(ident) if const_params.contains(&ident));
+    let is_const_arg =
+        |tree: &TokenTree| matches!(tree, TokenTree::Ident(_) | TokenTree::Literal(_));
+    let first: Vec<TokenTree> = first.into_iter().collect();
+    let second: Vec<TokenTree> = second.into_iter().collect();
+    first.len() == second.len()
+        && first.iter().zip(&second).all(|(a, b)| match (a, b) {
+            (TokenTree::Group(a), TokenTree::Group(b)) => {
+                a.delimiter() == b.delimiter() && may_match(a.stream(), b.stream(), const_params)
+            }
+            _ if (is_param(a) && is_const_arg(b)) || (is_param(b) && is_const_arg(a)) => true,
+            _ => a.to_string() == b.to_string(),
+        })
+}
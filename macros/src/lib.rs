@@ -1,209 +1,1179 @@
+mod actix;
+mod attrs;
+mod boxing;
+mod bson;
+mod companion;
+mod compare;
+mod cow;
+mod display;
+mod flatten;
+mod generics;
+mod lifetimes;
+mod lints;
+mod normalize;
+mod ops;
+mod otel;
+mod redis;
+mod schema;
+mod serde;
+mod shared;
+mod sources;
+mod subset;
+mod targets;
+mod telemetry;
+mod transitions;
+mod ts;
+
+use attrs::{ContainerAttrs, Families, ValidateAttrs, VariantAttrs};
+use companion::Companions;
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use std::collections::HashMap;
-use syn::{Data, DeriveInput, Fields, Index, Meta, parse_macro_input};
+use syn::ext::IdentExt;
+use syn::{Data, DeriveInput, Fields, Index, Token, parse_macro_input, punctuated::Punctuated};
 
-/// Check if a variant has the #[type_enum(skip)] attribute
-fn has_skip_attribute(variant: &syn::Variant) -> bool {
-    for attr in &variant.attrs {
-        if attr.path().is_ident("type_enum") {
-            if let Meta::List(meta_list) = &attr.meta {
-                let tokens = meta_list.tokens.to_string();
-                if tokens == "skip" {
-                    return true;
-                }
+/// Convert a `CamelCase` identifier into `snake_case`, keeping runs of capitals like the `HTTP`
+/// of `HTTPServer` together as `http_server`
+///
+/// Raw identifiers like `r#Foo` are converted without their `r#`.
+fn snake_case(ident: &syn::Ident) -> String {
+    let chars: Vec<char> = ident.unraw().to_string().chars().collect();
+    let mut snake = String::new();
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() {
+            let after_word = i > 0 && !chars[i - 1].is_uppercase() && chars[i - 1] != '_';
+            let ends_acronym = i > 0
+                && chars[i - 1].is_uppercase()
+                && chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_word || ends_acronym {
+                snake.push('_');
             }
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
         }
     }
-    false
+    snake
+}
+
+/// Convert a `snake_case` or `kebab-case` name into `CamelCase`
+fn camel_case(name: &str) -> String {
+    name.split(['_', '-'])
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars))
+                .into_iter()
+                .flatten()
+        })
+        .collect()
+}
+
+/// The variant's `#[type_enum(rename = "...")]`, used as is for its serialized tag
+fn renamed(variant: &syn::Variant) -> Option<String> {
+    let attrs = VariantAttrs::parse(variant).ok()?;
+    attrs.rename.map(|rename| rename.value())
+}
+
+/// The identifier of the variant in its kind: its `#[type_enum(rename = "...")]` in
+/// `CamelCase`, or its own. Generated methods like `as_<variant>()` use it in `snake_case`.
+fn kind_ident(variant: &syn::Variant) -> syn::Ident {
+    match renamed(variant) {
+        Some(rename) => syn::Ident::new(&camel_case(&rename), variant.ident.span()),
+        None => variant.ident.clone(),
+    }
 }
 
 /// Get a canonical string representation of a type for duplicate detection
+///
+/// Named and tuple fields convert from the same payload type, so they share a key.
 fn type_key(fields: &Fields) -> String {
-    match fields {
-        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote!(#fields).to_string(),
-        Fields::Unnamed(fields) => {
-            let field_types: Vec<_> = fields.unnamed.iter().map(|f| &f.ty).collect();
-            quote!((#(#field_types),*)).to_string()
+    if fields.is_empty() {
+        String::new()
+    } else {
+        let payload = syn::parse2(payload_type(fields)).expect("payload types are types");
+        normalize::type_key(&payload)
+    }
+}
+
+/// The type a variant converts from: the field type, or a tuple of the field types
+fn payload_type(fields: &Fields) -> proc_macro2::TokenStream {
+    match fields.len() {
+        1 => {
+            let field_type = &fields.iter().next().unwrap().ty;
+            quote!(#field_type)
         }
-        _ => String::new(),
+        _ => {
+            let field_types = fields.iter().map(|f| &f.ty);
+            quote!((#(#field_types),*))
+        }
+    }
+}
+
+/// Names `field_0`, `field_1`, ... for binding each field of a variant
+fn field_bindings(fields: &Fields) -> Vec<syn::Ident> {
+    (0..fields.len())
+        .map(|i| format_ident!("field_{}", i))
+        .collect()
+}
+
+/// A `return Ok(...)` building `variant` from the `payload` binding, for the probing
+/// conversions like `TryFrom<Bson>`, behind the validator of a validated variant
+///
+/// The variant is built directly, since its `From` impl may be left out.
+fn probed_return(
+    ty: &proc_macro2::TokenStream,
+    variant: &syn::Variant,
+    validator: Option<&ValidateAttrs>,
+) -> proc_macro2::TokenStream {
+    let bindings = field_bindings(&variant.fields);
+    let constructor = variant_with_fields(ty, variant, &bindings);
+    let destructure = match &bindings[..] {
+        [binding] => quote!(#binding),
+        _ => quote!((#(#bindings),*)),
+    };
+    let build = quote! {
+        let #destructure = payload;
+        return Ok(#constructor);
+    };
+    match validator {
+        Some(ValidateAttrs { with, .. }) => quote! {
+            if #with(&payload).is_ok() {
+                #build
+            }
+        },
+        None => build,
     }
 }
 
+/// A pattern or constructor for `ty::Variant` with its fields bound to, or set from, `bindings`
+fn variant_with_fields(
+    ty: &proc_macro2::TokenStream,
+    variant: &syn::Variant,
+    bindings: &[impl quote::ToTokens],
+) -> proc_macro2::TokenStream {
+    let variant_name = &variant.ident;
+    match &variant.fields {
+        Fields::Unnamed(_) => quote!(#ty::#variant_name(#(#bindings),*)),
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|f| &f.ident);
+            quote!(#ty::#variant_name { #(#names: #bindings),* })
+        }
+        Fields::Unit => quote!(#ty::#variant_name),
+    }
+}
+
+/// The variant's `#[deprecated]` attributes, to repeat on items generated for it
+fn deprecated_attrs(variant: &syn::Variant) -> Vec<&syn::Attribute> {
+    variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("deprecated"))
+        .collect()
+}
+
+/// The key duplicate detection compares a variant's payload by
+fn duplicate_key(variant: &syn::Variant, attrs: &VariantAttrs) -> String {
+    // A variant declared `same_as` an alias compares as the aliased type
+    match &attrs.same_as {
+        Some(ty) => normalize::type_key(ty),
+        None => type_key(&variant.fields),
+    }
+}
+
+/// Skip the variants sharing their payload type with a `#[type_enum(primary)]` variant
+fn resolve_primary(
+    variants: &Punctuated<syn::Variant, Token![,]>,
+    variant_attrs: &mut [VariantAttrs],
+) -> syn::Result<()> {
+    let mut primaries: HashMap<String, &syn::Variant> = HashMap::new();
+    for (variant, attrs) in variants.iter().zip(variant_attrs.iter()) {
+        if attrs.skip || !attrs.primary {
+            continue;
+        }
+        let key = duplicate_key(variant, attrs);
+        if let Some(first) = primaries.insert(key, variant) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "variants `{}` and `{}` hold the same type and are both marked \
+                    #[type_enum(primary)]; only one of them can get the conversions",
+                    first.ident, variant.ident
+                ),
+            ));
+        }
+    }
+    for (variant, attrs) in variants.iter().zip(variant_attrs.iter_mut()) {
+        if !attrs.skip && !attrs.primary && primaries.contains_key(&duplicate_key(variant, attrs)) {
+            attrs.skip = true;
+        }
+    }
+    Ok(())
+}
+
+/// Generate `Default` for the enum from the variant marked `#[type_enum(default)]`, with each of
+/// its fields set to the field type's default
+fn default_impl(
+    input: &DeriveInput,
+    variants: &Punctuated<syn::Variant, Token![,]>,
+    variant_attrs: &[VariantAttrs],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut defaults = variants
+        .iter()
+        .zip(variant_attrs)
+        .filter(|(_, attrs)| attrs.default);
+    let Some((variant, attrs)) = defaults.next() else {
+        return Ok(quote! {});
+    };
+    if attrs.validate.is_some() {
+        return Err(syn::Error::new_spanned(
+            variant,
+            format!(
+                "`{}` is marked #[type_enum(validate(...))], so its default fields would skip \
+                the validator; it can't also be #[type_enum(default)]",
+                variant.ident
+            ),
+        ));
+    }
+    let mut defaults = defaults.map(|(variant, _)| variant);
+    if let Some(second) = defaults.next() {
+        return Err(syn::Error::new_spanned(
+            second,
+            format!(
+                "variants `{}` and `{}` are both marked #[type_enum(default)]; only one of them \
+                can be the default",
+                variant.ident, second.ident
+            ),
+        ));
+    }
+
+    let name = &input.ident;
+    let generics = generics::with_bounds(
+        &input.generics,
+        variant.fields.iter().map(|field| &field.ty),
+        &quote!(::core::default::Default),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let values: Vec<_> = variant
+        .fields
+        .iter()
+        .map(|_| quote!(::core::default::Default::default()))
+        .collect();
+    let constructor = variant_with_fields(&quote!(#name), variant, &values);
+    Ok(quote! {
+        impl #impl_generics ::core::default::Default for #name #ty_generics #where_clause {
+            fn default() -> Self {
+                #constructor
+            }
+        }
+    })
+}
+
 #[proc_macro_derive(TypeEnum, attributes(type_enum))]
 pub fn type_enum_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let input = &generics::resolve_self(input);
     let name = &input.ident;
 
     let data = match &input.data {
         Data::Enum(data) => data,
-        _ => panic!("TypeEnum can only be derived for enums"),
+        Data::Struct(data) => {
+            return Err(syn::Error::new_spanned(
+                data.struct_token,
+                format!(
+                    "TypeEnum can only be derived for enums; to convert between `{}` and other \
+                    types, hold it in a variant of an enum like `enum Value {{ {}({}), ... }}`",
+                    name, name, name
+                ),
+            ));
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "TypeEnum can only be derived for enums, not unions",
+            ));
+        }
     };
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            format!(
+                "TypeEnum needs at least one variant holding a payload, like `enum {} {{ Text(String) }}`",
+                name
+            ),
+        ));
+    }
 
-    // First pass: collect types and check for duplicates (excluding skipped variants)
-    let mut seen_types: HashMap<String, &syn::Variant> = HashMap::new();
+    let container_attrs = ContainerAttrs::parse(&input.attrs)?;
+    // Path to the `type_enum` crate in generated code
+    let krate = match &container_attrs.crate_path {
+        Some(path) => quote!(#path),
+        None => quote!(::type_enum),
+    };
+    let mut variant_attrs = data
+        .variants
+        .iter()
+        .map(VariantAttrs::parse)
+        .collect::<syn::Result<Vec<_>>>()?;
+    // Unit variants hold no payload to convert from, so they are always skipped
+    for (variant, attrs) in data.variants.iter().zip(&mut variant_attrs) {
+        if variant.fields.is_empty() {
+            attrs.skip = true;
+        }
+    }
+    resolve_primary(&data.variants, &mut variant_attrs)?;
+    let mut kind_idents: HashMap<String, &syn::Variant> = HashMap::new();
     for variant in &data.variants {
-        if has_skip_attribute(variant) {
-            continue;
+        let kind = kind_ident(variant);
+        if let Some(first) = kind_idents.insert(kind.to_string(), variant) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "variants `{}` and `{}` would both be named `{}` in the kind and generated \
+                    methods; pick another #[type_enum(rename = \"...\")]",
+                    first.ident, variant.ident, kind
+                ),
+            ));
         }
+    }
+
+    // Variants taking part in code generation, with #[type_enum(skip)] ones removed
+    let variants: Vec<&syn::Variant> = data
+        .variants
+        .iter()
+        .zip(&variant_attrs)
+        .filter(|(_, attrs)| !attrs.skip)
+        .map(|(variant, _)| variant)
+        .collect();
+
+    let all_variants: Vec<&syn::Variant> = data.variants.iter().collect();
+    lifetimes::check_lifetimes(name, &input.generics, &all_variants)?;
+    generics::check_supported(name, &input.generics, &container_attrs)?;
+    let families = &container_attrs.families;
+    families.check(&container_attrs)?;
 
-        let key = type_key(&variant.fields);
+    // First pass: collect types and check for duplicates
+    let mut seen_types: HashMap<String, &syn::Variant> = HashMap::new();
+    for (variant, attrs) in data.variants.iter().zip(&variant_attrs) {
+        if attrs.skip {
+            continue;
+        }
+        let key = duplicate_key(variant, attrs);
         if !key.is_empty() {
             if let Some(first_variant) = seen_types.get(&key) {
                 let first_name = &first_variant.ident;
                 let second_name = &variant.ident;
-                return syn::Error::new_spanned(
+                return Err(syn::Error::new_spanned(
                     variant,
                     format!(
                         "duplicate type in enum: variants `{}` and `{}` both hold the same type(s). \
-                        Each variant must hold a unique type. Use #[type_enum(skip)] to exclude a variant.",
+                        Each variant must hold a unique type. Use #[type_enum(skip)] to exclude a variant, \
+                        or #[type_enum(primary)] to pick the one that gets the conversions.",
                         first_name, second_name
                     ),
-                )
-                .to_compile_error()
-                .into();
+                ));
             }
             seen_types.insert(key, variant);
         }
     }
+    generics::check_overlap(&input.generics, &variants)?;
+
+    // The trait families generated for each of `variants`, after `only(...)`, `no_from` and
+    // `skip(...)`. A left-out `From` may be written by hand or by another derive, like
+    // thiserror's #[from].
+    let payload_variants: Vec<(&syn::Variant, Families)> = data
+        .variants
+        .iter()
+        .zip(&variant_attrs)
+        .filter(|(_, attrs)| !attrs.skip)
+        .map(|(variant, attrs)| (variant, families.restrict(&attrs.families)))
+        .collect();
 
     let mut from_impls = Vec::new();
     let mut trait_impls = Vec::new();
 
-    for variant in &data.variants {
-        // Skip variants with #[type_enum(skip)] attribute
-        if has_skip_attribute(variant) {
-            continue;
-        }
-
-        let variant_name = &variant.ident;
+    let ty = quote!(#name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let lifetime = generics::accessor_lifetime(&input.generics);
+    let accessor_generics = generics::with_accessor_lifetime(&input.generics, &lifetime);
+    let (accessor_impl_generics, _, _) = accessor_generics.split_for_impl();
+    for &(variant, families) in &payload_variants {
+        let (record_construction, record_failure) = if container_attrs.telemetry {
+            let counters = telemetry::counters(name, &krate, &all_variants, variant);
+            (
+                quote!(#counters.record_construction();),
+                quote!(#counters.record_failed_extraction();),
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
 
-        match &variant.fields {
-            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
-                // Single field tuple variant like Number(i64)
-                let field_type = &fields.unnamed[0].ty;
+        match variant.fields.len() {
+            0 => unreachable!("unit variants are skipped"),
+            1 => {
+                // Single field variant like Number(i64) or Id { id: u64 }
+                let field_type = &variant.fields.iter().next().unwrap().ty;
+                let pattern = variant_with_fields(&ty, variant, &[quote!(val)]);
+                let constructor = variant_with_fields(&ty, variant, &[quote!(value)]);
 
                 // Generate From implementation
-                from_impls.push(quote! {
-                    impl From<#field_type> for #name {
-                        fn from(value: #field_type) -> Self {
-                            #name::#variant_name(value)
+                if families.from {
+                    from_impls.push(quote! {
+                        impl #impl_generics From<#field_type> for #name #ty_generics #where_clause {
+                            fn from(value: #field_type) -> Self {
+                                #record_construction
+                                #constructor
+                            }
                         }
-                    }
-                });
+                    });
+                }
 
                 // Generate Value implementation for &'a T
-                trait_impls.push(quote! {
-                    impl<'a> crate::Value<'a, &'a #field_type> for #name {
-                        fn value(&'a self) -> Option<&'a #field_type> {
-                            match self {
-                                #name::#variant_name(val) => Some(val),
-                                _ => None,
+                if families.value {
+                    trait_impls.push(quote! {
+                        impl #accessor_impl_generics #krate::Value<#lifetime, &#lifetime #field_type> for #name #ty_generics #where_clause {
+                            fn value(&#lifetime self) -> Option<&#lifetime #field_type> {
+                                match self {
+                                    #pattern => Some(val),
+                                    _ => {
+                                        #record_failure
+                                        None
+                                    }
+                                }
                             }
                         }
-                    }
-                });
+                    });
+                }
 
                 // Generate ValueMut implementation for &'a mut T
-                trait_impls.push(quote! {
-                    impl<'a> crate::ValueMut<'a, &'a mut #field_type> for #name {
-                        fn value_mut(&'a mut self) -> Option<&'a mut #field_type> {
-                            match self {
-                                #name::#variant_name(val) => Some(val),
-                                _ => None,
+                if families.value_mut {
+                    trait_impls.push(quote! {
+                        impl #accessor_impl_generics #krate::ValueMut<#lifetime, &#lifetime mut #field_type> for #name #ty_generics #where_clause {
+                            fn value_mut(&#lifetime mut self) -> Option<&#lifetime mut #field_type> {
+                                match self {
+                                    #pattern => Some(val),
+                                    _ => {
+                                        #record_failure
+                                        None
+                                    }
+                                }
                             }
                         }
-                    }
-                });
+                    });
+                }
 
                 // Generate IntoValue implementation for T
-                trait_impls.push(quote! {
-                    impl crate::IntoValue<#field_type> for #name {
-                        fn into_value(self) -> Result<#field_type, Self> {
-                            match self {
-                                #name::#variant_name(val) => Ok(val),
-                                other => Err(other),
+                if families.into_value {
+                    trait_impls.push(quote! {
+                        impl #impl_generics #krate::IntoValue<#field_type> for #name #ty_generics #where_clause {
+                            fn into_value(self) -> Result<#field_type, Self> {
+                                match self {
+                                    #pattern => Ok(val),
+                                    other => {
+                                        #record_failure
+                                        Err(other)
+                                    }
+                                }
                             }
                         }
-                    }
-                });
+                    });
+                }
+
+                // Generate Holds<T>, naming &'a T and &'a mut T
+                if families.value || families.value_mut || families.into_value {
+                    trait_impls.push(quote! {
+                        impl #impl_generics #krate::Holds<#field_type> for #name #ty_generics #where_clause {
+                            type Ref<#lifetime> = &#lifetime #field_type where Self: #lifetime;
+                            type Mut<#lifetime> = &#lifetime mut #field_type where Self: #lifetime;
+                        }
+                    });
+                }
+
+                // Generate TryFrom<&'a Enum> for &'a T through Value
+                if families.try_from_enum
+                    && families.value
+                    && generics::converts_from_enum(input, quote!(#field_type))
+                {
+                    trait_impls.push(quote! {
+                        impl #accessor_impl_generics ::core::convert::TryFrom<&#lifetime #name #ty_generics> for &#lifetime #field_type #where_clause {
+                            type Error = &#lifetime #name #ty_generics;
+
+                            fn try_from(value: &#lifetime #name #ty_generics) -> Result<Self, Self::Error> {
+                                <#name #ty_generics as #krate::Value<#lifetime, Self>>::value(value).ok_or(value)
+                            }
+                        }
+                    });
+                }
+
+                // Generate TryFrom<Enum> for T through IntoValue
+                if families.try_from_enum
+                    && families.into_value
+                    && generics::converts_from_enum(input, quote!(#field_type))
+                {
+                    trait_impls.push(quote! {
+                        impl #impl_generics ::core::convert::TryFrom<#name #ty_generics> for #field_type #where_clause {
+                            type Error = #name #ty_generics;
+
+                            fn try_from(value: #name #ty_generics) -> Result<Self, Self::Error> {
+                                <#name #ty_generics as #krate::IntoValue<#field_type>>::into_value(value)
+                            }
+                        }
+                    });
+                }
             }
-            Fields::Unnamed(fields) if fields.unnamed.len() > 1 => {
-                // Multiple field tuple variant like Tuple(u8, u8)
-                let field_types: Vec<_> = fields.unnamed.iter().map(|f| &f.ty).collect();
+            len => {
+                // Multiple field variant like Tuple(u8, u8) or User { id: u64, name: String },
+                // converting from a tuple of the field types in declaration order
+                let field_types: Vec<_> = variant.fields.iter().map(|f| &f.ty).collect();
                 let tuple_type = quote! { (#(#field_types),*) };
 
-                let field_indices: Vec<Index> =
-                    (0..fields.unnamed.len()).map(Index::from).collect();
+                let field_indices: Vec<proc_macro2::TokenStream> = (0..len)
+                    .map(|i| {
+                        let index = Index::from(i);
+                        quote!(value.#index)
+                    })
+                    .collect();
+                let constructor = variant_with_fields(&ty, variant, &field_indices);
 
                 // Generate From implementation
-                from_impls.push(quote! {
-                    impl From<#tuple_type> for #name {
-                        fn from(value: #tuple_type) -> Self {
-                            #name::#variant_name(#(value.#field_indices),*)
+                if families.from {
+                    from_impls.push(quote! {
+                        impl #impl_generics From<#tuple_type> for #name #ty_generics #where_clause {
+                            fn from(value: #tuple_type) -> Self {
+                                #record_construction
+                                #constructor
+                            }
                         }
-                    }
-                });
+                    });
+                }
 
                 // Generate field names for destructuring
-                let field_names = (0..fields.unnamed.len())
-                    .map(|i| {
-                        syn::Ident::new(&format!("field_{}", i), proc_macro2::Span::call_site())
-                    })
-                    .collect::<Vec<_>>();
+                let field_names = field_bindings(&variant.fields);
+                let pattern = variant_with_fields(&ty, variant, &field_names);
 
                 // Generate Value implementation for (&'a T1, &'a T2, ...)
-                let ref_tuple_type = quote! { (#(&'a #field_types),*) };
-                trait_impls.push(quote! {
-                    impl<'a> crate::Value<'a, #ref_tuple_type> for #name {
-                        fn value(&'a self) -> Option<#ref_tuple_type> {
-                            match self {
-                                #name::#variant_name(#(#field_names),*) => Some((#(#field_names),*)),
-                                _ => None,
+                let ref_tuple_type = quote! { (#(&#lifetime #field_types),*) };
+                if families.value {
+                    trait_impls.push(quote! {
+                        impl #accessor_impl_generics #krate::Value<#lifetime, #ref_tuple_type> for #name #ty_generics #where_clause {
+                            fn value(&#lifetime self) -> Option<#ref_tuple_type> {
+                                match self {
+                                    #pattern => Some((#(#field_names),*)),
+                                    _ => {
+                                        #record_failure
+                                        None
+                                    }
+                                }
                             }
                         }
-                    }
-                });
+                    });
+                }
 
                 // Generate ValueMut implementation for (&'a mut T1, &'a mut T2, ...)
-                let mut_ref_tuple_type = quote! { (#(&'a mut #field_types),*) };
-                trait_impls.push(quote! {
-                    impl<'a> crate::ValueMut<'a, #mut_ref_tuple_type> for #name {
-                        fn value_mut(&'a mut self) -> Option<#mut_ref_tuple_type> {
-                            match self {
-                                #name::#variant_name(#(#field_names),*) => Some((#(#field_names),*)),
-                                _ => None,
+                let mut_ref_tuple_type = quote! { (#(&#lifetime mut #field_types),*) };
+                if families.value_mut {
+                    trait_impls.push(quote! {
+                        impl #accessor_impl_generics #krate::ValueMut<#lifetime, #mut_ref_tuple_type> for #name #ty_generics #where_clause {
+                            fn value_mut(&#lifetime mut self) -> Option<#mut_ref_tuple_type> {
+                                match self {
+                                    #pattern => Some((#(#field_names),*)),
+                                    _ => {
+                                        #record_failure
+                                        None
+                                    }
+                                }
                             }
                         }
-                    }
-                });
+                    });
+                }
 
                 // Generate IntoValue implementation for (T1, T2, ...)
-                trait_impls.push(quote! {
-                    impl crate::IntoValue<#tuple_type> for #name {
-                        fn into_value(self) -> Result<#tuple_type, Self> {
-                            match self {
-                                #name::#variant_name(#(#field_names),*) => Ok((#(#field_names),*)),
-                                other => Err(other),
+                if families.into_value {
+                    trait_impls.push(quote! {
+                        impl #impl_generics #krate::IntoValue<#tuple_type> for #name #ty_generics #where_clause {
+                            fn into_value(self) -> Result<#tuple_type, Self> {
+                                match self {
+                                    #pattern => Ok((#(#field_names),*)),
+                                    other => {
+                                        #record_failure
+                                        Err(other)
+                                    }
+                                }
                             }
                         }
-                    }
-                });
-            }
-            Fields::Unnamed(_) => {
-                panic!("Empty tuple variants are not supported");
+                    });
+                }
+
+                // Generate Holds<(T1, T2, ...)>, naming the tuples of references
+                if families.value || families.value_mut || families.into_value {
+                    trait_impls.push(quote! {
+                        impl #impl_generics #krate::Holds<#tuple_type> for #name #ty_generics #where_clause {
+                            type Ref<#lifetime> = #ref_tuple_type where Self: #lifetime;
+                            type Mut<#lifetime> = #mut_ref_tuple_type where Self: #lifetime;
+                        }
+                    });
+                }
+
+                // Generate TryFrom<&'a Enum> for (&'a T1, &'a T2, ...) through Value
+                if families.try_from_enum
+                    && families.value
+                    && generics::converts_from_enum(input, tuple_type.clone())
+                {
+                    trait_impls.push(quote! {
+                        impl #accessor_impl_generics ::core::convert::TryFrom<&#lifetime #name #ty_generics> for #ref_tuple_type #where_clause {
+                            type Error = &#lifetime #name #ty_generics;
+
+                            fn try_from(value: &#lifetime #name #ty_generics) -> Result<Self, Self::Error> {
+                                <#name #ty_generics as #krate::Value<#lifetime, Self>>::value(value).ok_or(value)
+                            }
+                        }
+                    });
+                }
+
+                // Generate TryFrom<Enum> for (T1, T2, ...) through IntoValue
+                if families.try_from_enum
+                    && families.into_value
+                    && generics::converts_from_enum(input, tuple_type.clone())
+                {
+                    trait_impls.push(quote! {
+                        impl #impl_generics ::core::convert::TryFrom<#name #ty_generics> for #tuple_type #where_clause {
+                            type Error = #name #ty_generics;
+
+                            fn try_from(value: #name #ty_generics) -> Result<Self, Self::Error> {
+                                <#name #ty_generics as #krate::IntoValue<#tuple_type>>::into_value(value)
+                            }
+                        }
+                    });
+                }
             }
-            _ => panic!(
-                "Only tuple variants are supported (struct-style variants are not supported)"
-            ),
         }
     }
 
-    let expanded = quote! {
+    // The variants the probing conversions like `TryFrom<Bson>` try, with their validators
+    let probed_variants: Vec<(&syn::Variant, Option<&ValidateAttrs>)> = variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .map(|(variant, attrs)| (*variant, attrs.validate.as_ref()))
+        .collect();
+
+    // Companion types cover every variant, including skipped ones
+    let bson_impls = match &container_attrs.bson {
+        Some(bson) => bson::bson_impls(name, &krate, &all_variants, &probed_variants, bson),
+        None => quote! {},
+    };
+    let otel_impl = if container_attrs.otel {
+        otel::otel_impl(name, &krate, &all_variants)
+    } else {
+        quote! {}
+    };
+    let companions = Companions::new(input, all_variants.clone(), &container_attrs);
+    let kind_name = container_attrs
+        .kind_name
+        .clone()
+        .unwrap_or_else(|| format_ident!("{}Kind", name));
+    let descriptions: Vec<_> = variant_attrs
+        .iter()
+        .map(|attrs| attrs.doc.clone())
+        .collect();
+    let mut companion_items = vec![companions.kind_enum(&kind_name, &descriptions)];
+    let ref_name = container_attrs.ref_enum.then(|| {
+        container_attrs
+            .ref_name
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Ref", name))
+    });
+    if let Some(ref_name) = &ref_name {
+        companion_items.push(companions.ref_enum(ref_name));
+    }
+    if container_attrs.mut_enum {
+        let mut_name = container_attrs
+            .mut_name
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Mut", name));
+        companion_items.push(companions.mut_enum(&mut_name));
+    }
+    if container_attrs.owned {
+        let owned_name = container_attrs
+            .owned_name
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Owned", name));
+        companion_items.push(companions.owned_enum(&owned_name)?);
+    }
+    // Variants built by unchecked constructors, which `no_from` and `validate(...)` leave out
+    let constructible: Vec<&syn::Variant> = payload_variants
+        .iter()
+        .filter(|(_, families)| families.from)
+        .map(|(variant, _)| *variant)
+        .collect();
+    let validators: Vec<Option<&ValidateAttrs>> = variant_attrs
+        .iter()
+        .map(|attrs| attrs.validate.as_ref())
+        .collect();
+    companion_items.push(companions.field_accessors(&variants));
+    companion_items.push(companions.wrapping_constructors(&constructible));
+    if container_attrs.const_constructors {
+        companion_items.push(companions.const_constructors(&variants, &constructible)?);
+    }
+    if container_attrs.is_methods {
+        companion_items.push(companions.is_methods(&krate));
+    }
+    if container_attrs.getters {
+        companion_items
+            .push(companions.getters(container_attrs.ref_enum, container_attrs.mut_enum)?);
+    }
+    let downcast_variants: Vec<&syn::Variant> = data
+        .variants
+        .iter()
+        .zip(&variant_attrs)
+        .filter(|(_, attrs)| attrs.downcast)
+        .map(|(variant, _)| variant)
+        .collect();
+    companion_items.push(companions.downcast(&downcast_variants));
+    let catch_alls: Vec<&syn::Variant> = data
+        .variants
+        .iter()
+        .zip(&variant_attrs)
+        .filter(|(_, attrs)| attrs.catch_all)
+        .map(|(variant, _)| variant)
+        .collect();
+    match catch_alls[..] {
+        [] => {}
+        [catch_all] => companion_items.push(companions.catch_all(catch_all, &constructible)?),
+        [_, second, ..] => {
+            return Err(syn::Error::new_spanned(
+                second,
+                "only one variant can be marked #[type_enum(catch_all)]",
+            ));
+        }
+    }
+    if container_attrs.discriminant {
+        companion_items.push(companions.discriminant());
+    }
+    let tags: Vec<_> = variant_attrs
+        .iter()
+        .map(|attrs| attrs.tag.clone())
+        .collect();
+    companion_items.push(companions.tags(&kind_name, &tags)?);
+    if container_attrs.visitor {
+        companion_items.push(companions.visitor());
+    }
+    if container_attrs.async_visitor {
+        companion_items.push(companions.async_visitor());
+    }
+    if container_attrs.handler {
+        companion_items.push(companions.handler());
+    }
+    if container_attrs.partition {
+        companion_items.push(companions.partition());
+    }
+    if container_attrs.parts {
+        companion_items.push(companions.parts(&kind_name, &validators));
+    }
+    let companion_items = companions.wrap(companion_items);
+    let kind_path = companions.path(&kind_name);
+
+    let actix_impls = match &container_attrs.actix {
+        Some(actix) => actix::actix_impls(name, &krate, &input.vis, &all_variants, actix)?,
+        None => quote! {},
+    };
+    let boxed_variants: Vec<(&syn::Variant, Families)> = payload_variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .filter(|(_, attrs)| attrs.boxed)
+        .map(|(payload, _)| *payload)
+        .collect();
+    let shared_variants: Vec<(&syn::Variant, Families)> = payload_variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .filter(|(_, attrs)| attrs.shared)
+        .map(|(payload, _)| *payload)
+        .collect();
+    let unwrapped = boxing::unwrapped_types(
+        &payload_variants,
+        container_attrs.auto_box_over.is_some(),
+        &boxed_variants,
+        &shared_variants,
+    );
+    let auto_box_impls = match container_attrs.auto_box_over {
+        Some(limit) => boxing::auto_box(
+            input,
+            &krate,
+            &kind_path,
+            limit,
+            &payload_variants,
+            &variants,
+            &unwrapped,
+        )?,
+        None => boxing::boxed(
+            input,
+            &krate,
+            &kind_path,
+            &boxed_variants,
+            &variants,
+            &unwrapped,
+        )?,
+    };
+    let shared_impls = shared::shared(
+        input,
+        &krate,
+        &kind_path,
+        &shared_variants,
+        &variants,
+        &unwrapped,
+    )?;
+    let source_variants: Vec<(&syn::Variant, &VariantAttrs)> = payload_variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .filter(|(_, attrs)| !attrs.from.is_empty() || !attrs.try_from.is_empty())
+        .map(|((variant, families), attrs)| {
+            if families.from || attrs.from.is_empty() {
+                Ok((*variant, attrs))
+            } else {
+                Err(syn::Error::new_spanned(
+                    variant,
+                    format!(
+                        "`#[type_enum(from(...))]` on `{}` needs its `From` impl, which is left out",
+                        variant.ident
+                    ),
+                ))
+            }
+        })
+        .collect::<syn::Result<_>>()?;
+    let source_impls =
+        sources::source_impls(input, &krate, &kind_path, &source_variants, &variants)?;
+    let validated_variants: Vec<_> = variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .filter_map(|(variant, attrs)| Some((*variant, attrs.validate.as_ref()?)))
+        .collect();
+    let validated_impls = sources::validated_impls(input, &krate, &kind_path, &validated_variants)?;
+    let target_variants: Vec<(&syn::Variant, &[syn::Type])> = payload_variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .filter(|((_, families), attrs)| families.value && !attrs.as_ref.is_empty())
+        .map(|((variant, _), attrs)| (*variant, &attrs.as_ref[..]))
+        .collect();
+    let target_impls = targets::target_impls(input, &krate, &target_variants, &variants)?;
+    let cow_impls = cow::cow_impls(input, &krate, &kind_path, &payload_variants);
+    let flatten_macro = flatten::flatten_macro(input, &payload_variants);
+    let flattened: Vec<(&syn::Variant, Families)> = payload_variants
+        .iter()
+        .zip(variant_attrs.iter().filter(|attrs| !attrs.skip))
+        .filter(|(_, attrs)| attrs.flatten)
+        .map(|(pair, _)| *pair)
+        .collect();
+    let flatten_impls = flatten::flatten_impls(input, &flattened)?;
+    let serde_variants: Vec<&syn::Variant> = data
+        .variants
+        .iter()
+        .zip(&variant_attrs)
+        .filter(|(_, attrs)| !attrs.serde_skip)
+        .map(|(variant, _)| variant)
+        .collect();
+    let serde_impls = match &container_attrs.serde {
+        Some(serde) => {
+            let ref_path = ref_name.as_ref().map(|ref_name| companions.path(ref_name));
+            serde::serde_impls(
+                name,
+                &krate,
+                ref_path,
+                &all_variants,
+                &serde_variants,
+                &validators,
+                serde,
+            )
+        }
+        None => quote! {},
+    };
+    let ts_impl = if container_attrs.ts {
+        ts::ts_impl(
+            name,
+            &krate,
+            &serde_variants,
+            container_attrs.serde.as_ref(),
+        )
+    } else {
+        quote! {}
+    };
+    let cmp_impls = if container_attrs.payload_eq || container_attrs.payload_cmp {
+        let cmp_variants: Vec<&syn::Variant> = data
+            .variants
+            .iter()
+            .zip(&variant_attrs)
+            .filter(|(_, attrs)| !attrs.skip && !attrs.no_cmp)
+            .map(|(variant, _)| variant)
+            .collect();
+        compare::payload_cmp(name, &cmp_variants, container_attrs.payload_cmp)
+    } else {
+        quote! {}
+    };
+    let ord_impls = if container_attrs.delegate_ord {
+        compare::delegate_ord(input, &all_variants, &tags)?
+    } else {
+        quote! {}
+    };
+    let telemetry_impl = if container_attrs.telemetry {
+        telemetry::telemetry_impl(name, &krate, &all_variants)
+    } else {
+        quote! {}
+    };
+    let redis_impls = if container_attrs.redis {
+        redis::redis_impls(name, &krate, &all_variants, &probed_variants)
+    } else {
+        quote! {}
+    };
+    let transition_impls = transitions::transitions(
+        name,
+        &krate,
+        &input.vis,
+        &kind_path,
+        &container_attrs.transitions,
+        &variants,
+        &all_variants,
+    )?;
+
+    let subset_impls = subset::subset_impls(
+        name,
+        &krate,
+        &container_attrs.subset_of,
+        &variants,
+        &all_variants,
+    )?;
+
+    let ops_variants: Vec<&syn::Variant> = data
+        .variants
+        .iter()
+        .zip(&variant_attrs)
+        .filter(|(_, attrs)| !attrs.skip && !attrs.no_ops)
+        .map(|(variant, _)| variant)
+        .collect();
+    let op_impls = ops::delegate_ops(
+        name,
+        &krate,
+        &kind_path,
+        &container_attrs.delegate_ops,
+        &ops_variants,
+    )?;
+
+    // `Payload` requires `Into<Enum>`, which a payload left out of `From` may not provide
+    let payload_impls = payload_variants
+        .iter()
+        .filter(|(_, families)| families.from)
+        .map(|(variant, _)| {
+            let payload = payload_type(&variant.fields);
+            let kind_variant = kind_ident(variant);
+            quote! {
+                impl #impl_generics #krate::Payload<#name #ty_generics> for #payload #where_clause {
+                    const KIND: #kind_path = #kind_path::#kind_variant;
+                }
+            }
+        });
+
+    // `Contains` is a marker, so every payload gets it whichever families are left out
+    let contains_impls = payload_variants.iter().map(|(variant, _)| {
+        let payload = payload_type(&variant.fields);
+        quote! {
+            impl #impl_generics #krate::Contains<#payload> for #name #ty_generics #where_clause {}
+        }
+    });
+
+    let variant_name_arms = data.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let name_str = variant_name.to_string();
+        quote!(#name::#variant_name { .. } => #name_str)
+    });
+
+    let payload_type_name_arms = data.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let payload = payload_type(&variant.fields);
+        quote!(#name::#variant_name { .. } => ::core::any::type_name::<#payload>())
+    });
+
+    // Type-level list of payload types, built back to front
+    let type_list = variants
+        .iter()
+        .rev()
+        .fold(quote!(#krate::type_list::Nil), |tail, variant| {
+            let payload = payload_type(&variant.fields);
+            quote!(#krate::type_list::Cons<#payload, #tail>)
+        });
+    let kind_variants = data.variants.iter().map(kind_ident);
+    let schema_hash = schema::schema_hash(
+        name,
+        &data.variants.iter().zip(&variant_attrs).collect::<Vec<_>>(),
+        container_attrs.serde.as_ref(),
+    );
+    let type_enum_impl = quote! {
+        impl #impl_generics #krate::TypeEnum for #name #ty_generics #where_clause {
+            type Types = #type_list;
+            type Kind = #kind_path;
+            const KINDS: &'static [#kind_path] = &[#(#kind_path::#kind_variants),*];
+            const SCHEMA_HASH: u64 = #schema_hash;
+
+            fn kind(&self) -> #kind_path {
+                self.kind()
+            }
+
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    #(#variant_name_arms),*
+                }
+            }
+
+            fn payload_type_name(&self) -> &'static str {
+                match self {
+                    #(#payload_type_name_arms),*
+                }
+            }
+        }
+
+        #(#payload_impls)*
+        #(#contains_impls)*
+    };
+
+    let roundtrip_tests = if container_attrs.test_roundtrip {
+        let roundtrip_variants: Vec<&syn::Variant> = payload_variants
+            .iter()
+            .filter(|(_, families)| families.from && families.value && families.into_value)
+            .map(|(variant, _)| *variant)
+            .collect();
+        roundtrip_tests(
+            name,
+            &krate,
+            container_attrs.roundtrip_format.as_ref(),
+            &roundtrip_variants,
+        )
+    } else {
+        quote! {}
+    };
+
+    let default_impl = default_impl(input, &data.variants, &variant_attrs)?;
+    let display_formats: Vec<_> = variant_attrs
+        .iter()
+        .map(|attrs| attrs.display.clone())
+        .collect();
+    let display_impl = if container_attrs.display
+        || container_attrs.error
+        || display_formats.iter().any(Option::is_some)
+    {
+        display::display_impl(input, &all_variants, &display_formats)?
+    } else {
+        quote! {}
+    };
+    let error_impl = if container_attrs.error {
+        display::error_impl(input, &all_variants)
+    } else {
+        quote! {}
+    };
+
+    lints::mark_generated(quote! {
         #(#from_impls)*
         #(#trait_impls)*
-    };
+        #auto_box_impls
+        #shared_impls
+        #cow_impls
+        #flatten_macro
+        #flatten_impls
+        #source_impls
+        #validated_impls
+        #target_impls
+        #default_impl
+        #display_impl
+        #error_impl
+        #type_enum_impl
+        #companion_items
+        #op_impls
+        #cmp_impls
+        #ord_impls
+        #serde_impls
+        #ts_impl
+        #bson_impls
+        #otel_impl
+        #redis_impls
+        #telemetry_impl
+        #transition_impls
+        #subset_impls
+        #actix_impls
+        #roundtrip_tests
+    })
+}
 
-    TokenStream::from(expanded)
+/// Generate a `#[cfg(test)]` module checking that every variant survives `From` followed by
+/// `Value` and `IntoValue`, using `Default` payloads
+///
+/// With a serde `format`, each value must also come back equal from its `to_string` and
+/// `from_str`.
+fn roundtrip_tests(
+    name: &syn::Ident,
+    krate: &proc_macro2::TokenStream,
+    format: Option<&syn::Path>,
+    variants: &[&syn::Variant],
+) -> proc_macro2::TokenStream {
+    let module = format_ident!("__type_enum_roundtrip_{}", snake_case(name));
+
+    let tests = variants.iter().filter_map(|variant| {
+        let test_name = format_ident!("{}_roundtrip", snake_case(&kind_ident(variant)));
+        if variant.fields.is_empty() {
+            return None;
+        }
+        let field_types: Vec<_> = variant.fields.iter().map(|f| &f.ty).collect();
+
+        let (payload_type, ref_type, expected_ref) = if field_types.len() == 1 {
+            let field_type = field_types[0];
+            (quote!(#field_type), quote!(&#field_type), quote!(&expected))
+        } else {
+            let field_names: Vec<_> = (0..field_types.len())
+                .map(|i| format_ident!("field_{}", i))
+                .collect();
+            (
+                quote!((#(#field_types),*)),
+                quote!((#(&#field_types),*)),
+                quote!({
+                    let (#(#field_names),*) = &expected;
+                    (#(#field_names),*)
+                }),
+            )
+        };
+
+        let serde_roundtrip = format.map(|format| {
+            quote! {
+                let encoded = #format::to_string(&value).expect("serializing failed");
+                let decoded: #name = #format::from_str(&encoded).expect("deserializing failed");
+                assert_eq!(decoded, value);
+            }
+        });
+
+        Some(quote! {
+            #[test]
+            fn #test_name() {
+                let expected: #payload_type = ::core::default::Default::default();
+                let value: #name = <#payload_type as ::core::default::Default>::default().into();
+                assert_eq!(
+                    <#name as #krate::Value<'_, #ref_type>>::value(&value),
+                    Some(#expected_ref)
+                );
+                #serde_roundtrip
+                assert_eq!(
+                    <#name as #krate::IntoValue<#payload_type>>::into_value(value).ok(),
+                    Some(expected)
+                );
+            }
+        })
+    });
+
+    quote! {
+        #[cfg(test)]
+        mod #module {
+            use super::*;
+
+            #(#tests)*
+        }
+    }
 }
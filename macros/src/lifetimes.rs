@@ -0,0 +1,103 @@
+use syn::{GenericArgument, Generics, Lifetime, PathArguments, Type, TypeReference};
+
+/// Find references in the field types of `variants` whose lifetime the enum doesn't declare
+///
+/// Without this check the user sees the missing lifetime reported again from inside every
+/// generated impl, which hides the actual fix.
+pub fn check_lifetimes(
+    name: &syn::Ident,
+    generics: &Generics,
+    variants: &[&syn::Variant],
+) -> syn::Result<()> {
+    let declared: Vec<&Lifetime> = generics.lifetimes().map(|param| &param.lifetime).collect();
+    for variant in variants {
+        for field in &variant.fields {
+            if let Some(problem) = find_problem(&field.ty, &declared) {
+                return Err(problem.into_error(name, &declared));
+            }
+        }
+    }
+    Ok(())
+}
+
+enum Problem<'t> {
+    /// A reference without a lifetime, or with `'_`
+    Elided(&'t TypeReference),
+    /// A lifetime the enum doesn't declare
+    Undeclared(&'t Lifetime),
+}
+
+impl Problem<'_> {
+    fn into_error(self, name: &syn::Ident, declared: &[&Lifetime]) -> syn::Error {
+        match self {
+            Problem::Elided(reference) => {
+                let message = match declared.first() {
+                    Some(lifetime) => format!(
+                        "reference payloads need a named lifetime, like `&{} T`",
+                        lifetime
+                    ),
+                    None => format!(
+                        "reference payloads need a lifetime parameter: declare one like \
+                        `enum {}<'a>` and use `&'a T` in this field",
+                        name
+                    ),
+                };
+                syn::Error::new_spanned(reference, message)
+            }
+            Problem::Undeclared(lifetime) => syn::Error::new_spanned(
+                lifetime,
+                format!(
+                    "lifetime `{}` is not declared on the enum: add it like `enum {}<{}>`",
+                    lifetime, name, lifetime
+                ),
+            ),
+        }
+    }
+}
+
+fn check_lifetime<'t>(lifetime: &'t Lifetime, declared: &[&Lifetime]) -> Option<Problem<'t>> {
+    if lifetime.ident == "static" || declared.contains(&lifetime) {
+        None
+    } else {
+        Some(Problem::Undeclared(lifetime))
+    }
+}
+
+fn find_problem<'t>(ty: &'t Type, declared: &[&Lifetime]) -> Option<Problem<'t>> {
+    match ty {
+        Type::Reference(reference) => match &reference.lifetime {
+            None => Some(Problem::Elided(reference)),
+            Some(lifetime) if lifetime.ident == "_" => Some(Problem::Elided(reference)),
+            Some(lifetime) => check_lifetime(lifetime, declared),
+        }
+        .or_else(|| find_problem(&reference.elem, declared)),
+        Type::Path(path) => {
+            let qself = path
+                .qself
+                .as_ref()
+                .and_then(|qself| find_problem(&qself.ty, declared));
+            qself.or_else(|| {
+                path.path.segments.iter().find_map(|segment| {
+                    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                        return None;
+                    };
+                    args.args.iter().find_map(|arg| match arg {
+                        GenericArgument::Lifetime(lifetime) => check_lifetime(lifetime, declared),
+                        GenericArgument::Type(ty) => find_problem(ty, declared),
+                        _ => None,
+                    })
+                })
+            })
+        }
+        Type::Tuple(tuple) => tuple
+            .elems
+            .iter()
+            .find_map(|elem| find_problem(elem, declared)),
+        Type::Array(array) => find_problem(&array.elem, declared),
+        Type::Slice(slice) => find_problem(&slice.elem, declared),
+        Type::Ptr(ptr) => find_problem(&ptr.elem, declared),
+        Type::Paren(paren) => find_problem(&paren.elem, declared),
+        Type::Group(group) => find_problem(&group.elem, declared),
+        _ => None,
+    }
+}
@@ -0,0 +1,60 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Item, parse_quote};
+
+/// Mark every generated item as generated code
+///
+/// Impls get `#[automatically_derived]`, and every item allows the lints that strict downstream
+/// crates commonly enable, since users can't fix code they didn't write.
+pub fn mark_generated(tokens: TokenStream) -> syn::Result<TokenStream> {
+    let mut file: syn::File = syn::parse2(tokens)?;
+    for item in &mut file.items {
+        mark_item(item);
+    }
+    let items = &file.items;
+    Ok(quote! { #(#items)* })
+}
+
+fn allow_attr() -> Attribute {
+    parse_quote! {
+        #[allow(
+            dead_code,
+            deprecated,
+            missing_docs,
+            unreachable_patterns,
+            unused_qualifications,
+            unused_results,
+            single_use_lifetimes,
+            elided_lifetimes_in_paths,
+            clippy::all,
+            clippy::pedantic,
+            clippy::nursery,
+            clippy::restriction
+        )]
+    }
+}
+
+fn mark_item(item: &mut Item) {
+    let attrs = match item {
+        Item::Impl(item_impl) => {
+            item_impl
+                .attrs
+                .insert(0, parse_quote!(#[automatically_derived]));
+            &mut item_impl.attrs
+        }
+        Item::Mod(item_mod) => {
+            if let Some((_, items)) = &mut item_mod.content {
+                items.iter_mut().for_each(mark_item);
+            }
+            &mut item_mod.attrs
+        }
+        Item::Const(item) => &mut item.attrs,
+        Item::Enum(item) => &mut item.attrs,
+        Item::Fn(item) => &mut item.attrs,
+        Item::Struct(item) => &mut item.attrs,
+        Item::Trait(item) => &mut item.attrs,
+        Item::Use(item) => &mut item.attrs,
+        _ => return,
+    };
+    attrs.push(allow_attr());
+}
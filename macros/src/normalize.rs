@@ -0,0 +1,105 @@
+use quote::ToTokens;
+use syn::{GenericArgument, PathArguments, ReturnType, Type};
+
+/// Prelude types reachable under a module of `std`, `core` or `alloc`, as `(module, name)`
+const PRELUDE_TYPES: &[(&str, &str)] = &[
+    ("string", "String"),
+    ("vec", "Vec"),
+    ("boxed", "Box"),
+    ("option", "Option"),
+    ("result", "Result"),
+];
+
+/// The standard library crates a path can start with
+const STD_CRATES: &[&str] = &["std", "core", "alloc"];
+
+/// A canonical spelling of `ty` for comparing payload types
+///
+/// Paths to prelude types like `std::string::String` and `::core::primitive::u8` become `String`
+/// and `u8`, and parentheses around a type are dropped. Spacing is already uniform in token
+/// strings, so `Vec<u8>` and `Vec< u8 >` compare equal as well.
+pub fn type_key(ty: &Type) -> String {
+    let mut ty = ty.clone();
+    normalize(&mut ty);
+    ty.to_token_stream().to_string()
+}
+
+fn normalize(ty: &mut Type) {
+    match ty {
+        Type::Paren(paren) => {
+            let mut inner = (*paren.elem).clone();
+            normalize(&mut inner);
+            *ty = inner;
+        }
+        Type::Group(group) => {
+            let mut inner = (*group.elem).clone();
+            normalize(&mut inner);
+            *ty = inner;
+        }
+        Type::Path(path) => {
+            if let Some(qself) = &mut path.qself {
+                normalize(&mut qself.ty);
+            } else {
+                strip_std_prefix(&mut path.path);
+            }
+            for segment in &mut path.path.segments {
+                normalize_arguments(&mut segment.arguments);
+            }
+        }
+        Type::Reference(reference) => normalize(&mut reference.elem),
+        Type::Ptr(ptr) => normalize(&mut ptr.elem),
+        Type::Slice(slice) => normalize(&mut slice.elem),
+        Type::Array(array) => normalize(&mut array.elem),
+        Type::Tuple(tuple) => tuple.elems.iter_mut().for_each(normalize),
+        Type::BareFn(function) => {
+            for input in &mut function.inputs {
+                normalize(&mut input.ty);
+            }
+            if let ReturnType::Type(_, output) = &mut function.output {
+                normalize(output);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn normalize_arguments(arguments: &mut PathArguments) {
+    match arguments {
+        PathArguments::AngleBracketed(args) => {
+            for arg in &mut args.args {
+                if let GenericArgument::Type(ty) = arg {
+                    normalize(ty);
+                }
+            }
+        }
+        PathArguments::Parenthesized(args) => {
+            args.inputs.iter_mut().for_each(normalize);
+            if let ReturnType::Type(_, output) = &mut args.output {
+                normalize(output);
+            }
+        }
+        PathArguments::None => {}
+    }
+}
+
+/// Shorten `std::string::String` and the like to the name the prelude gives them
+fn strip_std_prefix(path: &mut syn::Path) {
+    let [krate, module, name] = path.segments.iter().collect::<Vec<_>>()[..] else {
+        return;
+    };
+    let is_std = STD_CRATES.iter().any(|std| krate.ident == std)
+        && krate.arguments.is_none()
+        && module.arguments.is_none();
+    let module = module.ident.to_string();
+    let name_str = name.ident.to_string();
+    let in_prelude = module == "primitive"
+        || PRELUDE_TYPES.iter().any(|(prelude_module, prelude_name)| {
+            *prelude_module == module && *prelude_name == name_str
+        });
+    if is_std && in_prelude {
+        let name = name.clone();
+        path.leading_colon = None;
+        path.segments.clear();
+        path.segments.push(name);
+    }
+}
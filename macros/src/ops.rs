@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Ident;
+
+use crate::variant_with_fields;
+
+/// Binary operators supported by `delegate_ops`, with the method of each trait
+const OPERATORS: &[(&str, &str)] = &[
+    ("Add", "add"),
+    ("Sub", "sub"),
+    ("Mul", "mul"),
+    ("Div", "div"),
+    ("Rem", "rem"),
+    ("BitAnd", "bitand"),
+    ("BitOr", "bitor"),
+    ("BitXor", "bitxor"),
+    ("Shl", "shl"),
+    ("Shr", "shr"),
+];
+
+/// Generate `core::ops` impls applying each operator to the payloads when both operands hold
+/// the same variant, and returning an `OpError` otherwise
+pub fn delegate_ops(
+    name: &Ident,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    ops: &[Ident],
+    variants: &[&syn::Variant],
+) -> syn::Result<TokenStream> {
+    let mut impls = Vec::new();
+    for op in ops {
+        let Some((_, method)) = OPERATORS.iter().find(|(trait_name, _)| op == trait_name) else {
+            let supported: Vec<_> = OPERATORS
+                .iter()
+                .map(|(trait_name, _)| *trait_name)
+                .collect();
+            return Err(syn::Error::new_spanned(
+                op,
+                format!(
+                    "unsupported operator `{}`, expected one of: {}",
+                    op,
+                    supported.join(", ")
+                ),
+            ));
+        };
+        let method = format_ident!("{}", method);
+        let op_name = op.to_string();
+
+        let ty = quote!(#name);
+        let arms = variants.iter().map(|variant| {
+            let len = variant.fields.len();
+            let lhs: Vec<_> = (0..len).map(|i| format_ident!("lhs_{}", i)).collect();
+            let rhs: Vec<_> = (0..len).map(|i| format_ident!("rhs_{}", i)).collect();
+            let results: Vec<_> = lhs
+                .iter()
+                .zip(&rhs)
+                .map(|(lhs, rhs)| quote!(::core::ops::#op::#method(#lhs, #rhs)))
+                .collect();
+            let lhs_pattern = variant_with_fields(&ty, variant, &lhs);
+            let rhs_pattern = variant_with_fields(&ty, variant, &rhs);
+            let result = variant_with_fields(&ty, variant, &results);
+            quote! {
+                (#lhs_pattern, #rhs_pattern) => ::core::result::Result::Ok(#result),
+            }
+        });
+
+        impls.push(quote! {
+            impl ::core::ops::#op for #name {
+                type Output = ::core::result::Result<#name, #krate::OpError<#kind_path>>;
+
+                fn #method(self, rhs: Self) -> Self::Output {
+                    let (lhs_kind, rhs_kind) = (self.kind(), rhs.kind());
+                    #[allow(unreachable_patterns)]
+                    match (self, rhs) {
+                        #(#arms)*
+                        _ => ::core::result::Result::Err(#krate::OpError {
+                            op: #op_name,
+                            lhs: lhs_kind,
+                            rhs: rhs_kind,
+                        }),
+                    }
+                }
+            }
+        });
+    }
+    Ok(quote! { #(#impls)* })
+}
@@ -0,0 +1,61 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, Ident};
+
+use crate::{field_bindings, variant_with_fields};
+
+/// Generate `From<Enum> for opentelemetry::Value` covering every variant
+///
+/// Single payloads convert with `Value::from` when possible and fall back to their `Display`
+/// output. Multi-field variants are recorded as a string of their converted fields, and unit
+/// variants as their name.
+pub fn otel_impl(name: &Ident, krate: &TokenStream, variants: &[&syn::Variant]) -> TokenStream {
+    let ty = quote!(#name);
+    let arms = variants.iter().map(|variant| {
+        let bindings = field_bindings(&variant.fields);
+        let pattern = variant_with_fields(&ty, variant, &bindings);
+        let fields = bindings
+            .iter()
+            .map(|binding| quote!(#krate::__private::OtelPayload(#binding).into_otel_value()));
+        let value = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                quote!(#krate::__private::OtelPayload(field_0).into_otel_value())
+            }
+            Fields::Unnamed(_) => {
+                let format = format!("({})", vec!["{}"; bindings.len()].join(", "));
+                quote!(#krate::__private::opentelemetry::Value::String(
+                    format!(#format, #(#fields),*).into()
+                ))
+            }
+            Fields::Named(named) => {
+                let entries: Vec<_> = named
+                    .named
+                    .iter()
+                    .map(|f| format!("{}: {{}}", f.ident.as_ref().unwrap()))
+                    .collect();
+                let format = format!("{{{{ {} }}}}", entries.join(", "));
+                quote!(#krate::__private::opentelemetry::Value::String(
+                    format!(#format, #(#fields),*).into()
+                ))
+            }
+            Fields::Unit => {
+                let variant_name =
+                    crate::renamed(variant).unwrap_or_else(|| variant.ident.to_string());
+                quote!(#krate::__private::opentelemetry::Value::String(#variant_name.into()))
+            }
+        };
+        quote!(#pattern => #value)
+    });
+
+    quote! {
+        impl From<#name> for #krate::__private::opentelemetry::Value {
+            fn from(value: #name) -> Self {
+                #[allow(unused_imports)]
+                use #krate::__private::{DisplayPayload as _, FromPayload as _};
+                match value {
+                    #(#arms,)*
+                }
+            }
+        }
+    }
+}
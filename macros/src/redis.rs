@@ -0,0 +1,95 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Ident;
+
+use crate::attrs::ValidateAttrs;
+use crate::{field_bindings, payload_type, probed_return, variant_with_fields};
+
+/// Generate `ToRedisArgs` forwarding to the held payload for every variant, and
+/// `FromRedisValue` trying the payload types of `variants` in declaration order
+///
+/// A validated variant only takes payloads passing its validator.
+pub fn redis_impls(
+    name: &Ident,
+    krate: &TokenStream,
+    all_variants: &[&syn::Variant],
+    variants: &[(&syn::Variant, Option<&ValidateAttrs>)],
+) -> TokenStream {
+    let ty = quote!(#name);
+    let mut write_arms = Vec::new();
+    let mut count_arms = Vec::new();
+    let mut numeric_arms = Vec::new();
+    for variant in all_variants {
+        let bindings = field_bindings(&variant.fields);
+        let pattern = variant_with_fields(&ty, variant, &bindings);
+        write_arms.push(quote! {
+            #pattern => {
+                #(#krate::__private::redis::ToRedisArgs::write_redis_args(#bindings, out);)*
+            }
+        });
+        count_arms.push(quote! {
+            #pattern => 0 #(+ #krate::__private::redis::ToRedisArgs::num_of_args(#bindings))*
+        });
+        if bindings.len() == 1 {
+            numeric_arms.push(quote! {
+                #pattern => #krate::__private::redis::ToRedisArgs::describe_numeric_behavior(#(#bindings)*)
+            });
+        }
+    }
+
+    let payloads = variants
+        .iter()
+        .map(|(variant, _)| payload_type(&variant.fields));
+    let returns = variants
+        .iter()
+        .map(|&(variant, validator)| probed_return(&ty, variant, validator));
+    let error = format!("no payload type of `{}` matches the value", name);
+
+    quote! {
+        impl #krate::__private::redis::ToRedisArgs for #name {
+            fn write_redis_args<W>(&self, out: &mut W)
+            where
+                W: ?Sized + #krate::__private::redis::RedisWrite,
+            {
+                match self {
+                    #(#write_arms)*
+                }
+            }
+
+            fn describe_numeric_behavior(&self) -> #krate::__private::redis::NumericBehavior {
+                match self {
+                    #(#numeric_arms,)*
+                    _ => #krate::__private::redis::NumericBehavior::NonNumeric,
+                }
+            }
+
+            fn num_of_args(&self) -> usize {
+                match self {
+                    #(#count_arms),*
+                }
+            }
+        }
+
+        impl #krate::__private::redis::FromRedisValue for #name {
+            /// Convert into the first variant whose payload type accepts the value
+            fn from_redis_value_ref(
+                value: &#krate::__private::redis::Value,
+            ) -> Result<Self, #krate::__private::redis::ParsingError> {
+                #(
+                    if let Ok(payload) =
+                        <#payloads as #krate::__private::redis::FromRedisValue>::from_redis_value_ref(value)
+                    {
+                        #returns
+                    }
+                )*
+                Err(format!("{}: {:?}", #error, value).into())
+            }
+
+            fn from_redis_value(
+                value: #krate::__private::redis::Value,
+            ) -> Result<Self, #krate::__private::redis::ParsingError> {
+                Self::from_redis_value_ref(&value)
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use quote::quote;
+use syn::{Fields, Ident};
+
+use crate::attrs::{SerdeAttrs, VariantAttrs};
+use crate::serde::rename_variant;
+
+/// Hash a canonical description of the enum: its name, each variant's name, fields and payload
+/// types, which variants are skipped, their `#[type_enum(tag = N)]` wire tags, and with `serde`,
+/// the tag and content fields and every tag
+///
+/// Uses 64-bit FNV-1a, which has no process-specific seed, so the result is the same in every
+/// build of the same definition.
+pub fn schema_hash(
+    name: &Ident,
+    variants: &[(&syn::Variant, &VariantAttrs)],
+    serde: Option<&SerdeAttrs>,
+) -> u64 {
+    let mut schema = format!("enum {name}");
+    if let Some(serde) = serde {
+        let value = |attr: &Option<syn::LitStr>, default: &str| {
+            attr.as_ref()
+                .map_or_else(|| default.to_string(), |lit| lit.value())
+        };
+        schema.push_str(&format!(
+            " serde(tag = {:?}, content = {:?})",
+            value(&serde.tag, "type"),
+            value(&serde.content, "value")
+        ));
+    }
+    for (variant, attrs) in variants {
+        schema.push_str(&format!("; {}", variant.ident));
+        match &variant.fields {
+            Fields::Unnamed(fields) => {
+                let types = fields.unnamed.iter().map(|f| {
+                    let ty = &f.ty;
+                    quote!(#ty).to_string()
+                });
+                schema.push_str(&format!("({})", types.collect::<Vec<_>>().join(", ")));
+            }
+            Fields::Named(fields) => {
+                let fields = fields.named.iter().map(|f| {
+                    let (field, ty) = (&f.ident, &f.ty);
+                    quote!(#field: #ty).to_string()
+                });
+                schema.push_str(&format!("{{ {} }}", fields.collect::<Vec<_>>().join(", ")));
+            }
+            Fields::Unit => {}
+        }
+        if attrs.skip {
+            schema.push_str(" skip");
+        }
+        if let Some(tag) = &attrs.tag {
+            // Parsed as a `u16` already; hashed by value, so `0x100` and `256` agree
+            let tag: u16 = tag
+                .base10_parse()
+                .expect("checked when parsing the attribute");
+            schema.push_str(&format!(" tag = {tag}"));
+        }
+        if let Some(serde) = serde {
+            if attrs.serde_skip {
+                schema.push_str(" serde(skip)");
+            } else {
+                let variant_name = variant.ident.to_string();
+                let tag = match (&attrs.rename, &serde.rename_all) {
+                    (Some(rename), _) => rename.value(),
+                    (None, Some(rule)) => rename_variant(&rule.value(), &variant_name),
+                    (None, None) => variant_name,
+                };
+                schema.push_str(&format!(" = {tag:?}"));
+            }
+        }
+    }
+
+    schema.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,218 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, Ident};
+
+use crate::attrs::{SerdeAttrs, ValidateAttrs};
+use crate::{field_bindings, variant_with_fields};
+
+/// Generate `Serialize` and `Deserialize` impls representing each variant as its tag next to its
+/// payload, like `{"type": "Number", "value": 1}`, and with `ref_path`, the same `Serialize` impl
+/// for the borrowed mirror enum
+///
+/// The impls go through private mirror enums with serde's own derive, so the representation is
+/// exactly serde's adjacently tagged one. A variant's `#[type_enum(rename = "...")]` is its tag,
+/// overriding `rename_all`. Variants left out of `variants` fail to serialize, and their tags are
+/// unknown when deserializing. Payloads of variants with `#[type_enum(validate(...))]` go
+/// through the validator when deserializing, whose error must implement `Display`.
+///
+/// `validators` holds each variant's validator, in declaration order like `all_variants`.
+pub fn serde_impls(
+    name: &Ident,
+    krate: &TokenStream,
+    ref_path: Option<TokenStream>,
+    all_variants: &[&syn::Variant],
+    variants: &[&syn::Variant],
+    validators: &[Option<&ValidateAttrs>],
+    attrs: &SerdeAttrs,
+) -> TokenStream {
+    let tag = attrs
+        .tag
+        .as_ref()
+        .map_or_else(|| "type".to_string(), |tag| tag.value());
+    let content = attrs
+        .content
+        .as_ref()
+        .map_or_else(|| "value".to_string(), |content| content.value());
+    let rename_all = attrs
+        .rename_all
+        .as_ref()
+        .map(|rule| quote!(, rename_all = #rule));
+    let serde_path = format!("{}::__private::serde", krate);
+    let serde_attr = quote! {
+        #[serde(crate = #serde_path, tag = #tag, content = #content #rename_all)]
+    };
+
+    let ty = quote!(#name);
+    let shadow = quote!(__TypeEnumShadow);
+    let has_fields = variants.iter().any(|variant| !variant.fields.is_empty());
+    let shadow_generics = if has_fields { quote!(<'a>) } else { quote!() };
+    let shadow_variants = |borrowed: bool| {
+        variants.iter().map(move |variant| {
+            let variant_name = &variant.ident;
+            let reference = if borrowed { quote!(&'a) } else { quote!() };
+            // With `rename_all`, every tag is spelled out, so serde's own rule doesn't run on
+            // names it can't handle like `Ärger`
+            let rename = crate::renamed(variant)
+                .or_else(|| {
+                    let rule = attrs.rename_all.as_ref()?;
+                    Some(rename_variant(&rule.value(), &variant.ident.to_string()))
+                })
+                .map(|tag| quote!(#[serde(rename = #tag)]));
+            match &variant.fields {
+                Fields::Unnamed(fields) => {
+                    let types = fields.unnamed.iter().map(|f| &f.ty);
+                    quote!(#rename #variant_name(#(#reference #types),*))
+                }
+                Fields::Named(fields) => {
+                    let names = fields.named.iter().map(|f| &f.ident);
+                    let types = fields.named.iter().map(|f| &f.ty);
+                    quote!(#rename #variant_name { #(#names: #reference #types),* })
+                }
+                Fields::Unit => quote!(#rename #variant_name),
+            }
+        })
+    };
+    let owned_variants = shadow_variants(false);
+
+    // Matching on `&EnumRef` binds references to its references, so those fields are dereferenced
+    let serialize_impl = |target: TokenStream, source: &TokenStream, deref: bool| {
+        let arms = all_variants.iter().map(|variant| {
+            let bindings = field_bindings(&variant.fields);
+            let pattern = variant_with_fields(source, variant, &bindings);
+            if variants
+                .iter()
+                .any(|serialized| serialized.ident == variant.ident)
+            {
+                let fields: Vec<TokenStream> = bindings
+                    .iter()
+                    .map(|binding| {
+                        if deref {
+                            quote!(*#binding)
+                        } else {
+                            quote!(#binding)
+                        }
+                    })
+                    .collect();
+                let shadow_value = variant_with_fields(&shadow, variant, &fields);
+                quote!(#pattern => #shadow_value)
+            } else {
+                let message = format!("variant `{}` is excluded from serialization", variant.ident);
+                quote! {
+                    #pattern => {
+                        return Err(<S::Error as #krate::__private::serde::ser::Error>::custom(
+                            #message,
+                        ));
+                    }
+                }
+            }
+        });
+        let borrowed_variants = shadow_variants(true);
+        quote! {
+            impl #target {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: #krate::__private::serde::Serializer,
+                {
+                    #[derive(#krate::__private::serde::Serialize)]
+                    #serde_attr
+                    enum #shadow #shadow_generics {
+                        #(#borrowed_variants),*
+                    }
+
+                    let shadow = match self {
+                        #(#arms,)*
+                    };
+                    #krate::__private::serde::Serialize::serialize(&shadow, serializer)
+                }
+            }
+        }
+    };
+    let owned_serialize = serialize_impl(
+        quote!(#krate::__private::serde::Serialize for #name),
+        &ty,
+        false,
+    );
+    let ref_serialize = ref_path.map(|ref_path| {
+        serialize_impl(
+            quote!(<'a> #krate::__private::serde::Serialize for #ref_path<'a>),
+            &ref_path,
+            true,
+        )
+    });
+    let from_shadow = variants.iter().map(|variant| {
+        let bindings = field_bindings(&variant.fields);
+        let shadow_pattern = variant_with_fields(&shadow, variant, &bindings);
+        let value = variant_with_fields(&ty, variant, &bindings);
+        let validator = all_variants
+            .iter()
+            .zip(validators)
+            .find(|(declared, _)| declared.ident == variant.ident)
+            .and_then(|(_, validator)| *validator);
+        match validator {
+            Some(ValidateAttrs { with, .. }) => quote! {
+                #shadow_pattern => match #with(&#(#bindings),*) {
+                    Ok(()) => #value,
+                    Err(source) => {
+                        return Err(<D::Error as #krate::__private::serde::de::Error>::custom(source));
+                    }
+                }
+            },
+            None => quote!(#shadow_pattern => #value),
+        }
+    });
+
+    quote! {
+        #owned_serialize
+        #ref_serialize
+
+        impl<'de> #krate::__private::serde::Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: #krate::__private::serde::Deserializer<'de>,
+            {
+                #[derive(#krate::__private::serde::Deserialize)]
+                #serde_attr
+                enum #shadow {
+                    #(#owned_variants),*
+                }
+
+                let shadow =
+                    <#shadow as #krate::__private::serde::Deserialize>::deserialize(deserializer)?;
+                Ok(match shadow {
+                    #(#from_shadow),*
+                })
+            }
+        }
+    }
+}
+
+/// Apply one of serde's `rename_all` rules to a variant name, the way serde does
+pub fn rename_variant(rule: &str, variant: &str) -> String {
+    let snake = || {
+        let mut snake = String::new();
+        for (i, ch) in variant.char_indices() {
+            if i > 0 && ch.is_uppercase() {
+                snake.push('_');
+            }
+            snake.push(ch.to_ascii_lowercase());
+        }
+        snake
+    };
+    match rule {
+        "lowercase" => variant.to_ascii_lowercase(),
+        "UPPERCASE" => variant.to_ascii_uppercase(),
+        "camelCase" => {
+            // Split on the first `char`, as a byte slice would panic on a name like `Ärger`
+            let mut chars = variant.chars();
+            chars
+                .next()
+                .map(|first| first.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "snake_case" => snake(),
+        "SCREAMING_SNAKE_CASE" => snake().to_ascii_uppercase(),
+        "kebab-case" => snake().replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => snake().to_ascii_uppercase().replace('_', "-"),
+        _ => variant.to_string(),
+    }
+}
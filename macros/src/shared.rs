@@ -0,0 +1,84 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Type};
+
+use crate::attrs::Families;
+use crate::boxing::{check_clash, pointee, single_type};
+use crate::{generics, variant_with_fields};
+
+/// Generate the impls for the variants marked `#[type_enum(shared)]`
+///
+/// A variant holding an `Arc<T>` or `Rc<T>` also converts from a `T`, gives access to the `&T`
+/// behind the pointer, and hands out clones of the pointer through `SharedValue`.
+pub fn shared(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    shared: &[(&syn::Variant, Families)],
+    variants: &[&syn::Variant],
+    unwrapped: &[(&syn::Variant, Type)],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let lifetime = generics::accessor_lifetime(&input.generics);
+    let accessor_generics = generics::with_accessor_lifetime(&input.generics, &lifetime);
+    let (accessor_impl_generics, _, _) = accessor_generics.split_for_impl();
+
+    let mut items = Vec::new();
+    for &(variant, families) in shared {
+        let field_type = single_type(variant);
+        let Some((pointer, inner)) = field_type.as_ref().and_then(|ty| {
+            let inner = pointee(ty, "Arc").or_else(|| pointee(ty, "Rc"))?;
+            Some((ty, inner))
+        }) else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(shared)]` needs `{}` to hold a single `Arc<T>` or `Rc<T>` field",
+                    variant.ident
+                ),
+            ));
+        };
+        check_clash(variant, inner, variants, unwrapped)?;
+
+        let kind_variant = crate::kind_ident(variant);
+        let pattern = variant_with_fields(&quote!(#name), variant, &[quote!(val)]);
+        let constructor =
+            variant_with_fields(&quote!(#name), variant, &[quote!(<#pointer>::new(value))]);
+        if families.from {
+            items.push(quote! {
+                impl #impl_generics From<#inner> for #name #ty_generics #where_clause {
+                    fn from(value: #inner) -> Self {
+                        #constructor
+                    }
+                }
+
+                impl #impl_generics #krate::Payload<#name #ty_generics> for #inner #where_clause {
+                    const KIND: #kind_path = #kind_path::#kind_variant;
+                }
+            });
+        }
+        if families.value {
+            items.push(quote! {
+                impl #accessor_impl_generics #krate::Value<#lifetime, &#lifetime #inner> for #name #ty_generics #where_clause {
+                    fn value(&#lifetime self) -> Option<&#lifetime #inner> {
+                        match self {
+                            #pattern => Some(&**val),
+                            _ => None,
+                        }
+                    }
+                }
+
+                impl #impl_generics #krate::SharedValue<#pointer> for #name #ty_generics #where_clause {
+                    fn value_shared(&self) -> Option<#pointer> {
+                        match self {
+                            #pattern => Some(::core::clone::Clone::clone(val)),
+                            _ => None,
+                        }
+                    }
+                }
+            });
+        }
+    }
+    Ok(quote! { #(#items)* })
+}
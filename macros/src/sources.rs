@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::DeriveInput;
+
+use crate::attrs::{ValidateAttrs, VariantAttrs};
+use crate::boxing::single_type;
+use crate::{normalize, variant_with_fields};
+
+/// Generate the impls for the extra source types listed with `#[type_enum(from(...))]` and
+/// `#[type_enum(try_from(...))]`
+///
+/// A `from` source converts into the payload with `Into`, like `&str` into the `String` of
+/// `Text(String)`, and a `try_from` source with `TryFrom`, failing with a `TryFromError`. A source
+/// can't be the payload type of any variant, and only one variant can list it.
+pub fn source_impls(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    sources: &[(&syn::Variant, &VariantAttrs)],
+    variants: &[&syn::Variant],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut listed: HashMap<String, &syn::Variant> = HashMap::new();
+    let mut items = Vec::new();
+    for &(variant, attrs) in sources {
+        let Some(field_type) = single_type(variant) else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(from(...))]` and `#[type_enum(try_from(...))]` need `{}` to \
+                    hold a single field",
+                    variant.ident
+                ),
+            ));
+        };
+        let fallible = attrs.try_from.iter().map(|source| (source, true));
+        for (source, is_fallible) in attrs
+            .from
+            .iter()
+            .map(|source| (source, false))
+            .chain(fallible)
+        {
+            let key = normalize::type_key(source);
+            let holder = variants
+                .iter()
+                .find(|other| single_type(other).is_some_and(|ty| normalize::type_key(&ty) == key));
+            if let Some(holder) = holder {
+                return Err(syn::Error::new_spanned(
+                    source,
+                    format!(
+                        "duplicate type in enum: `{}` is the type held by `{}`",
+                        quote!(#source),
+                        holder.ident
+                    ),
+                ));
+            }
+            if let Some(first) = listed.insert(key, variant) {
+                return Err(syn::Error::new_spanned(
+                    source,
+                    format!(
+                        "`{}` is listed as a source of both `{}` and `{}`",
+                        quote!(#source),
+                        first.ident,
+                        variant.ident
+                    ),
+                ));
+            }
+
+            if is_fallible {
+                let kind_variant = crate::kind_ident(variant);
+                let constructor = variant_with_fields(&quote!(#name), variant, &[quote!(payload)]);
+                items.push(quote! {
+                    impl #impl_generics ::core::convert::TryFrom<#source> for #name #ty_generics #where_clause {
+                        type Error = #krate::TryFromError<
+                            #kind_path,
+                            <#field_type as ::core::convert::TryFrom<#source>>::Error,
+                        >;
+
+                        fn try_from(value: #source) -> Result<Self, Self::Error> {
+                            match <#field_type as ::core::convert::TryFrom<#source>>::try_from(value) {
+                                Ok(payload) => Ok(#constructor),
+                                Err(source) => Err(#krate::TryFromError {
+                                    kind: #kind_path::#kind_variant,
+                                    source,
+                                }),
+                            }
+                        }
+                    }
+                });
+            } else {
+                let constructor = variant_with_fields(
+                    &quote!(#name),
+                    variant,
+                    &[quote!(<#source as ::core::convert::Into<#field_type>>::into(value))],
+                );
+                items.push(quote! {
+                    impl #impl_generics From<#source> for #name #ty_generics #where_clause {
+                        fn from(value: #source) -> Self {
+                            #constructor
+                        }
+                    }
+                });
+            }
+        }
+    }
+    Ok(quote! { #(#items)* })
+}
+
+/// Generate the checked conversions of the variants with `#[type_enum(validate(...))]`
+///
+/// `TryFrom<Payload>` and `try_new_<variant>()` run the validator before building the variant,
+/// failing with a `TryFromError` holding its error. The unchecked `From` is left out.
+pub fn validated_impls(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    kind_path: &TokenStream,
+    validated: &[(&syn::Variant, &ValidateAttrs)],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut items = Vec::new();
+    for &(variant, validate) in validated {
+        let Some(field_type) = single_type(variant) else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(validate(...))]` needs `{}` to hold a single field",
+                    variant.ident
+                ),
+            ));
+        };
+        let ValidateAttrs { with, error } = validate;
+        let kind_variant = crate::kind_ident(variant);
+        let constructor = variant_with_fields(&quote!(#name), variant, &[quote!(value)]);
+        let method = format_ident!("try_new_{}", crate::snake_case(&kind_variant));
+        let doc = format!(
+            "Build a [`{}::{}`] if `value` passes `{}`",
+            name,
+            variant.ident,
+            quote!(#with).to_string().replace(' ', "")
+        );
+        let error_type = quote!(#krate::TryFromError<#kind_path, #error>);
+        items.push(quote! {
+            impl #impl_generics ::core::convert::TryFrom<#field_type> for #name #ty_generics #where_clause {
+                type Error = #error_type;
+
+                fn try_from(value: #field_type) -> Result<Self, Self::Error> {
+                    match #with(&value) {
+                        Ok(()) => Ok(#constructor),
+                        Err(source) => Err(#krate::TryFromError {
+                            kind: #kind_path::#kind_variant,
+                            source,
+                        }),
+                    }
+                }
+            }
+
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #doc]
+                #vis fn #method(value: #field_type) -> Result<Self, #error_type> {
+                    <Self as ::core::convert::TryFrom<#field_type>>::try_from(value)
+                }
+            }
+        });
+    }
+    Ok(quote! { #(#items)* })
+}
@@ -0,0 +1,89 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, Type};
+
+use crate::{field_bindings, payload_type, variant_with_fields};
+
+/// Generate the conversions between the enum and each enum in `#[type_enum(subset_of(...))]`
+///
+/// `From<Small> for Big` goes through `Big`'s `From` impl for each payload, and
+/// `TryFrom<Big> for Small` through its `IntoValue` impls, handing back the `Big` if it holds
+/// none of `Small`'s types. Unit variants map to the unit variant of the same name.
+pub fn subset_impls(
+    name: &Ident,
+    krate: &TokenStream,
+    supersets: &[Type],
+    variants: &[&syn::Variant],
+    all_variants: &[&syn::Variant],
+) -> syn::Result<TokenStream> {
+    if supersets.is_empty() {
+        return Ok(quote! {});
+    }
+    if let Some(skipped) = all_variants.iter().find(|variant| {
+        !variant.fields.is_empty() && !variants.iter().any(|kept| kept.ident == variant.ident)
+    }) {
+        return Err(syn::Error::new_spanned(
+            skipped,
+            format!(
+                "variant `{}` is skipped and has no payload type to convert through \
+                `subset_of`",
+                skipped.ident
+            ),
+        ));
+    }
+
+    let ty = quote!(#name);
+    let mut items = Vec::new();
+    for superset in supersets {
+        let mut widen_arms = Vec::new();
+        let mut narrow_steps = Vec::new();
+        for variant in all_variants {
+            let variant_name = &variant.ident;
+            if variant.fields.is_empty() {
+                widen_arms.push(quote!(#name::#variant_name => <#superset>::#variant_name));
+                narrow_steps.push(quote! {
+                    let value = match value {
+                        <#superset>::#variant_name => return Ok(#name::#variant_name),
+                        other => other,
+                    };
+                });
+                continue;
+            }
+            let payload = payload_type(&variant.fields);
+            let bindings = field_bindings(&variant.fields);
+            let pattern = variant_with_fields(&ty, variant, &bindings);
+            let constructor = variant_with_fields(&ty, variant, &bindings);
+            widen_arms.push(quote! {
+                #pattern => <#superset as ::core::convert::From<#payload>>::from((#(#bindings),*))
+            });
+            narrow_steps.push(quote! {
+                let value = match #krate::IntoValue::<#payload>::into_value(value) {
+                    Ok((#(#bindings),*)) => return Ok(#constructor),
+                    Err(value) => value,
+                };
+            });
+        }
+
+        items.push(quote! {
+            impl ::core::convert::From<#name> for #superset {
+                #[allow(unused_parens)]
+                fn from(value: #name) -> Self {
+                    match value {
+                        #(#widen_arms),*
+                    }
+                }
+            }
+
+            impl ::core::convert::TryFrom<#superset> for #name {
+                type Error = #superset;
+
+                #[allow(unused_parens)]
+                fn try_from(value: #superset) -> Result<Self, Self::Error> {
+                    #(#narrow_steps)*
+                    Err(value)
+                }
+            }
+        });
+    }
+    Ok(quote! { #(#items)* })
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Type};
+
+use crate::boxing::single_type;
+use crate::{cow, generics, normalize, variant_with_fields};
+
+/// Generate the `Value` impls for the targets listed with `#[type_enum(as_ref(...))]`
+///
+/// A target is borrowed from the payload with `AsRef`, like `&str` from the `String` of
+/// `Name(String)`. Only one variant can lend out a target, and not one that a `Cow` variant
+/// already lends out.
+pub fn target_impls(
+    input: &DeriveInput,
+    krate: &TokenStream,
+    targets: &[(&syn::Variant, &[Type])],
+    variants: &[&syn::Variant],
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let lifetime = generics::accessor_lifetime(&input.generics);
+    let accessor_generics = generics::with_accessor_lifetime(&input.generics, &lifetime);
+    let (accessor_impl_generics, _, _) = accessor_generics.split_for_impl();
+
+    let mut lent: HashMap<String, &syn::Ident> = variants
+        .iter()
+        .filter_map(|variant| {
+            let ty = single_type(variant)?;
+            let (_, borrowed) = cow::cow_type(&ty)?;
+            Some((normalize::type_key(borrowed), &variant.ident))
+        })
+        .collect();
+    let mut items = Vec::new();
+    for &(variant, types) in targets {
+        let Some(field_type) = single_type(variant) else {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`#[type_enum(as_ref(...))]` needs `{}` to hold a single field",
+                    variant.ident
+                ),
+            ));
+        };
+        for target in types {
+            if let Some(first) = lent.insert(normalize::type_key(target), &variant.ident) {
+                return Err(syn::Error::new_spanned(
+                    target,
+                    format!(
+                        "`{}` and `{}` both lend out a `&{}`",
+                        first,
+                        variant.ident,
+                        quote!(#target)
+                    ),
+                ));
+            }
+            let pattern = variant_with_fields(&quote!(#name), variant, &[quote!(val)]);
+            items.push(quote! {
+                impl #accessor_impl_generics #krate::Value<#lifetime, &#lifetime #target> for #name #ty_generics #where_clause {
+                    fn value(&#lifetime self) -> Option<&#lifetime #target> {
+                        match self {
+                            #pattern => Some(<#field_type as ::core::convert::AsRef<#target>>::as_ref(val)),
+                            _ => None,
+                        }
+                    }
+                }
+            });
+        }
+    }
+    Ok(quote! { #(#items)* })
+}
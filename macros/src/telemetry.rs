@@ -0,0 +1,35 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Ident;
+
+/// The counters of `variant`, found by its position among every variant like the kinds
+pub fn counters(
+    name: &Ident,
+    krate: &TokenStream,
+    all_variants: &[&syn::Variant],
+    variant: &syn::Variant,
+) -> TokenStream {
+    let index = all_variants
+        .iter()
+        .position(|other| other.ident == variant.ident)
+        .expect("variant belongs to the enum");
+    quote!(<#name as #krate::telemetry::Telemetry>::counters()[#index])
+}
+
+/// Generate the `Telemetry` impl holding one set of counters per variant in a static
+pub fn telemetry_impl(
+    name: &Ident,
+    krate: &TokenStream,
+    all_variants: &[&syn::Variant],
+) -> TokenStream {
+    let len = all_variants.len();
+    quote! {
+        impl #krate::telemetry::Telemetry for #name {
+            fn counters() -> &'static [#krate::telemetry::Counters] {
+                static COUNTERS: [#krate::telemetry::Counters; #len] =
+                    [const { #krate::telemetry::Counters::new() }; #len];
+                &COUNTERS
+            }
+        }
+    }
+}
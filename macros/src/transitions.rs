@@ -0,0 +1,84 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, Visibility};
+
+use crate::payload_type;
+
+/// Generate `Transition` impls for each declared `(from, to)` pair, plus the `TRANSITIONS` table
+/// and `can_transition_to()`
+///
+/// `variants` are the variants taking part in code generation; `all_variants` also includes
+/// skipped ones, to report them separately from unknown names.
+pub fn transitions(
+    name: &Ident,
+    krate: &TokenStream,
+    vis: &Visibility,
+    kind_path: &TokenStream,
+    pairs: &[(Ident, Ident)],
+    variants: &[&syn::Variant],
+    all_variants: &[&syn::Variant],
+) -> syn::Result<TokenStream> {
+    if pairs.is_empty() {
+        return Ok(quote! {});
+    }
+
+    let find = |state: &Ident| {
+        variants
+            .iter()
+            .find(|variant| variant.ident == *state)
+            .copied()
+            .ok_or_else(|| {
+                let message = if all_variants.iter().any(|variant| variant.ident == *state) {
+                    format!(
+                        "variant `{}` is skipped and can't take part in transitions",
+                        state
+                    )
+                } else {
+                    format!("unknown variant `{}` in transitions", state)
+                };
+                syn::Error::new_spanned(state, message)
+            })
+    };
+
+    let mut seen: Vec<(&Ident, &Ident)> = Vec::new();
+    let mut kinds = Vec::new();
+    let mut impls = Vec::new();
+    for (from, to) in pairs {
+        let from_variant = find(from)?;
+        let to_variant = find(to)?;
+        if seen.contains(&(from, to)) {
+            continue;
+        }
+        seen.push((from, to));
+        kinds.push((
+            crate::kind_ident(from_variant),
+            crate::kind_ident(to_variant),
+        ));
+        let from_type = payload_type(&from_variant.fields);
+        let to_type = payload_type(&to_variant.fields);
+        impls.push(quote! {
+            impl #krate::Transition<#from_type, #to_type> for #name {}
+        });
+    }
+
+    let table = kinds
+        .iter()
+        .map(|(from, to)| quote!((#kind_path::#from, #kind_path::#to)));
+
+    Ok(quote! {
+        #(#impls)*
+
+        impl #name {
+            /// Every declared transition, as `(from, to)` pairs of kinds
+            #vis const TRANSITIONS: &'static [(#kind_path, #kind_path)] = &[#(#table),*];
+
+            /// Whether a transition from the held variant to `to` was declared
+            #vis fn can_transition_to(&self, to: #kind_path) -> bool {
+                let from = self.kind();
+                Self::TRANSITIONS
+                    .iter()
+                    .any(|&(declared_from, declared_to)| declared_from == from && declared_to == to)
+            }
+        }
+    })
+}
@@ -0,0 +1,111 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, Ident, ext::IdentExt};
+
+use crate::attrs::SerdeAttrs;
+use crate::serde::rename_variant;
+
+/// Generate a `ts_rs::TS` impl declaring the enum as a union of the objects the `serde`
+/// representation produces, like `{ "type": "Number", "value": number }`
+///
+/// Only `variants` are part of the union, so variants excluded from serde are left out. Without
+/// `#[type_enum(serde)]` the union describes its default representation.
+pub fn ts_impl(
+    name: &Ident,
+    krate: &TokenStream,
+    variants: &[&syn::Variant],
+    serde: Option<&SerdeAttrs>,
+) -> TokenStream {
+    let serde_attr = |attr: fn(&SerdeAttrs) -> &Option<syn::LitStr>| {
+        serde.and_then(|serde| attr(serde).as_ref().map(|value| value.value()))
+    };
+    let tag = serde_attr(|serde| &serde.tag).unwrap_or_else(|| "type".to_string());
+    let content = serde_attr(|serde| &serde.content).unwrap_or_else(|| "value".to_string());
+    let rule = serde_attr(|serde| &serde.rename_all);
+
+    let ts_name = |ty: &syn::Type| quote!(<#ty as #krate::__private::ts_rs::TS>::name(cfg));
+    let members = variants.iter().map(|variant| {
+        let tag_value = match (crate::renamed(variant), &rule) {
+            (Some(rename), _) => rename,
+            (None, Some(rule)) => rename_variant(rule, &variant.ident.to_string()),
+            (None, None) => variant.ident.to_string(),
+        };
+        let tag_field = format!("\"{tag}\": \"{tag_value}\"");
+        let payload = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Some(ts_name(&fields.unnamed[0].ty))
+            }
+            Fields::Unnamed(fields) => {
+                let types = fields.unnamed.iter().map(|f| ts_name(&f.ty));
+                Some(quote!(format!("[{}]", [#(#types),*].join(", "))))
+            }
+            Fields::Named(fields) => {
+                let entries = fields.named.iter().map(|f| {
+                    let key = format!("{}: {{}}", f.ident.as_ref().unwrap().unraw());
+                    let ty = ts_name(&f.ty);
+                    quote!(format!(#key, #ty))
+                });
+                Some(quote!(format!("{{ {} }}", [#(#entries),*].join(", "))))
+            }
+            Fields::Unit => None,
+        };
+        match payload {
+            Some(payload) => {
+                let template = format!("{{{{ {tag_field}, \"{content}\": {{}} }}}}");
+                quote!(format!(#template, #payload))
+            }
+            None => {
+                let object = format!("{{ {tag_field} }}");
+                quote!(String::from(#object))
+            }
+        }
+    });
+    let field_types: Vec<&syn::Type> = variants
+        .iter()
+        .flat_map(|variant| variant.fields.iter().map(|f| &f.ty))
+        .collect();
+    let name_str = name.to_string();
+    let path = format!("{name}.ts");
+
+    quote! {
+        impl #krate::__private::ts_rs::TS for #name {
+            type WithoutGenerics = Self;
+            type OptionInnerType = Self;
+
+            fn name(_: &#krate::__private::ts_rs::Config) -> String {
+                String::from(#name_str)
+            }
+
+            fn decl(cfg: &#krate::__private::ts_rs::Config) -> String {
+                format!("type {} = {};", #name_str, Self::inline(cfg))
+            }
+
+            fn decl_concrete(cfg: &#krate::__private::ts_rs::Config) -> String {
+                Self::decl(cfg)
+            }
+
+            fn inline(cfg: &#krate::__private::ts_rs::Config) -> String {
+                let members: Vec<String> = vec![#(#members),*];
+                if members.is_empty() {
+                    String::from("never")
+                } else {
+                    members.join(" | ")
+                }
+            }
+
+            fn visit_dependencies(visitor: &mut impl #krate::__private::ts_rs::TypeVisitor)
+            where
+                Self: 'static,
+            {
+                #(
+                    visitor.visit::<#field_types>();
+                    <#field_types as #krate::__private::ts_rs::TS>::visit_generics(visitor);
+                )*
+            }
+
+            fn output_path() -> Option<std::path::PathBuf> {
+                Some(std::path::PathBuf::from(#path))
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Conversions between derived enums and `bson::Bson` values
+//!
+//! `#[type_enum(bson)]` generates `From<Enum> for Bson`, and `TryFrom<Bson> for Enum` which
+//! probes the payload types in declaration order using [`FromBson`]. With `bson(coerce)`, values
+//! that no payload type accepts exactly are probed again allowing [`FromBson::from_bson_coerce`]
+//! conversions.
+
+use ::bson::{Binary, Bson, DateTime, Document, oid::ObjectId};
+
+/// Payload types that can be read back from a [`Bson`] value
+pub trait FromBson: Sized {
+    /// Convert `value` if it is exactly the BSON type this type maps to
+    fn from_bson_strict(value: &Bson) -> Option<Self>;
+
+    /// Convert `value`, also accepting other BSON types that convert without loss
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        Self::from_bson_strict(value)
+    }
+}
+
+impl FromBson for Bson {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromBson for bool {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for i32 {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Int32(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Int32(n) => Some(*n),
+            Bson::Int64(n) => i32::try_from(*n).ok(),
+            Bson::Double(n)
+                if n.fract() == 0.0 && *n >= i32::MIN as f64 && *n <= i32::MAX as f64 =>
+            {
+                Some(*n as i32)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for i64 {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Int64(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Int32(n) => Some(i64::from(*n)),
+            Bson::Int64(n) => Some(*n),
+            // i64::MAX as f64 rounds up to 2^63, which is out of range
+            Bson::Double(n)
+                if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 =>
+            {
+                Some(*n as i64)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for f64 {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Double(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Double(n) => Some(*n),
+            Bson::Int32(n) => Some(f64::from(*n)),
+            Bson::Int64(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for String {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::String(s) | Bson::Symbol(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for Document {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Document(doc) => Some(doc.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for Binary {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Binary(binary) => Some(binary.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for ObjectId {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::ObjectId(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl FromBson for DateTime {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::DateTime(date) => Some(*date),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromBson> FromBson for Vec<T> {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Array(items) => items.iter().map(T::from_bson_strict).collect(),
+            _ => None,
+        }
+    }
+
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Array(items) => items.iter().map(T::from_bson_coerce).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromBson> FromBson for Option<T> {
+    fn from_bson_strict(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Null => Some(None),
+            other => T::from_bson_strict(other).map(Some),
+        }
+    }
+
+    fn from_bson_coerce(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Null | Bson::Undefined => Some(None),
+            other => T::from_bson_coerce(other).map(Some),
+        }
+    }
+}
+
+/// Multi-field payloads map to a BSON array with one element per field
+macro_rules! tuple_from_bson {
+    ($($field:ident $binding:ident),+) => {
+        impl<$($field: FromBson),+> FromBson for ($($field,)+) {
+            fn from_bson_strict(value: &Bson) -> Option<Self> {
+                match value {
+                    Bson::Array(items) => match items.as_slice() {
+                        [$($binding),+] => Some(($($field::from_bson_strict($binding)?,)+)),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+
+            fn from_bson_coerce(value: &Bson) -> Option<Self> {
+                match value {
+                    Bson::Array(items) => match items.as_slice() {
+                        [$($binding),+] => Some(($($field::from_bson_coerce($binding)?,)+)),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+tuple_from_bson!(A a, B b);
+tuple_from_bson!(A a, B b, C c);
+tuple_from_bson!(A a, B b, C c, D d);
+tuple_from_bson!(A a, B b, C c, D d, E e);
+tuple_from_bson!(A a, B b, C c, D d, E e, F f);
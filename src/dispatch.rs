@@ -0,0 +1,23 @@
+//! Dense function tables indexed by kind, built by [`dispatch_table!`](crate::dispatch_table)
+
+/// One function per variant of `E`, found by the position of the value's kind
+///
+/// A call is an index into the table followed by an indirect call, which is cheaper than a match
+/// for enums with many variants on hot paths. Build tables with
+/// [`dispatch_table!`](crate::dispatch_table), usually into a `static`.
+pub struct DispatchTable<E: 'static, R: 'static> {
+    entries: &'static [fn(&E) -> R],
+    index: fn(&E) -> usize,
+}
+
+impl<E, R> DispatchTable<E, R> {
+    #[doc(hidden)]
+    pub const fn new(entries: &'static [fn(&E) -> R], index: fn(&E) -> usize) -> Self {
+        DispatchTable { entries, index }
+    }
+
+    /// Call the function for the variant `value` holds
+    pub fn call(&self, value: &E) -> R {
+        (self.entries[(self.index)(value)])(value)
+    }
+}
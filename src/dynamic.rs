@@ -0,0 +1,69 @@
+//! An object-safe view of derived enums, for holding different enum types behind one trait object
+
+use std::any::Any;
+
+use crate::TypeEnum;
+
+/// Object-safe facade implemented by every derived enum
+///
+/// `Box<dyn DynTypeEnum>` can hold values of different enum types, which can be inspected by name
+/// and downcast back to their concrete type.
+pub trait DynTypeEnum: Any {
+    /// Name of the enum type
+    fn type_name(&self) -> &'static str;
+
+    /// Name of the variant the value holds
+    fn variant_name(&self) -> &'static str;
+
+    /// The value as `Any`, for downcasting to the concrete enum
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<E: TypeEnum + Any> DynTypeEnum for E {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<E>()
+    }
+
+    fn variant_name(&self) -> &'static str {
+        TypeEnum::variant_name(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl dyn DynTypeEnum {
+    /// Borrow the value as the concrete enum `E`, if it is one
+    pub fn downcast_ref<E: DynTypeEnum>(&self) -> Option<&E> {
+        self.as_any().downcast_ref()
+    }
+}
+
+/// A [`DynTypeEnum`] that can also be serialized, implemented by every derived enum that
+/// implements `Serialize`
+///
+/// `Box<dyn SerializeTypeEnum>` implements `serde::Serialize`, so collections of different enum
+/// types can be serialized together.
+#[cfg(feature = "erased-serde")]
+pub trait SerializeTypeEnum: DynTypeEnum + erased_serde::Serialize {}
+
+#[cfg(feature = "erased-serde")]
+impl<E: DynTypeEnum + serde::Serialize> SerializeTypeEnum for E {}
+
+#[cfg(feature = "erased-serde")]
+erased_serde::serialize_trait_object!(SerializeTypeEnum);
+
+/// A [`DynTypeEnum`] that can also be cloned, implemented by every derived enum that implements
+/// `Clone`
+///
+/// `Box<dyn CloneTypeEnum>` implements `Clone`, so collections of different enum types can be
+/// duplicated.
+#[cfg(feature = "dyn-clone")]
+pub trait CloneTypeEnum: DynTypeEnum + dyn_clone::DynClone {}
+
+#[cfg(feature = "dyn-clone")]
+impl<E: DynTypeEnum + Clone> CloneTypeEnum for E {}
+
+#[cfg(feature = "dyn-clone")]
+dyn_clone::clone_trait_object!(CloneTypeEnum);
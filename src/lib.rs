@@ -1,6 +1,240 @@
 // Re-export derive macro from `macros` sub-crate
+#[cfg(feature = "derive")]
 pub use macros::TypeEnum;
 
+// Generated code names the crate `::type_enum`, which has to resolve in its own tests too
+extern crate self as type_enum;
+
+#[cfg(feature = "bson")]
+pub mod bson;
+pub mod dispatch;
+pub mod dynamic;
+pub mod map;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(feature = "tower")]
+pub mod router;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod type_list;
+
+// Dependencies used by generated code, not part of the public API
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "otel")]
+    pub use crate::otel::{DisplayPayload, FromPayload, OtelPayload};
+    #[cfg(feature = "actix")]
+    pub use ::actix;
+    #[cfg(feature = "bson")]
+    pub use ::bson;
+    #[cfg(feature = "otel")]
+    pub use ::opentelemetry;
+    #[cfg(feature = "redis")]
+    pub use ::redis;
+    #[cfg(feature = "serde")]
+    pub use ::serde;
+    #[cfg(feature = "ts-rs")]
+    pub use ::ts_rs;
+}
+
+/// Implemented by the derive for every enum
+pub trait TypeEnum {
+    /// The payload types of the enum's variants, in declaration order, as a
+    /// [`type_list`] like `Cons<i64, Cons<String, Nil>>`
+    ///
+    /// Skipped variants are not included. Multi-field variants contribute a tuple.
+    type Types: type_list::TypeList;
+
+    /// The generated `<Name>Kind` enum, ordered like the variants are declared
+    type Kind: Copy + Ord + std::hash::Hash + std::fmt::Debug + 'static;
+
+    /// Every kind, in declaration order and including skipped variants
+    const KINDS: &'static [Self::Kind];
+
+    /// Fingerprint of the enum definition, covering its name, the variants with their payload
+    /// types and `#[type_enum(tag = N)]` tags, and the serde tags if the derive generates the
+    /// serde impls
+    ///
+    /// The hash is the same in every build of the same definition, so two ends of a protocol can
+    /// exchange it to check that they agree on the enum. Payload types are compared by how they
+    /// are written, so `String` and `std::string::String` hash differently.
+    const SCHEMA_HASH: u64;
+
+    /// Which variant this value holds
+    fn kind(&self) -> Self::Kind;
+
+    /// Name of the variant this value holds
+    fn variant_name(&self) -> &'static str;
+
+    /// Name of the payload type of the variant this value holds, as given by
+    /// [`std::any::type_name`], or `()` for a unit variant
+    fn payload_type_name(&self) -> &'static str;
+
+    /// If the enum holds a `T`, replace it with `f`'s result, which may be another payload type.
+    /// If not, return the enum unmodified as the error.
+    #[must_use = "the enum is only handed back in the result"]
+    fn map_payload<T, U>(self, f: impl FnOnce(T) -> U) -> Result<Self, Self>
+    where
+        Self: IntoValue<T> + From<U> + Sized,
+    {
+        self.into_value().map(|value| f(value).into())
+    }
+
+    /// Like [`map_payload`](TypeEnum::map_payload), with a fallible `f`
+    ///
+    /// The outer result reports whether the enum held a `T`; the inner one is `f`'s result.
+    #[must_use = "the enum is only handed back in the result"]
+    fn try_map_payload<T, U, E>(
+        self,
+        f: impl FnOnce(T) -> Result<U, E>,
+    ) -> Result<Result<Self, E>, Self>
+    where
+        Self: IntoValue<T> + From<U> + Sized,
+    {
+        self.into_value().map(|value| f(value).map(Self::from))
+    }
+
+    /// Call `f` with the held value if it is a `T`, then return the enum for further chaining
+    fn inspect_value<T>(&self, f: impl FnOnce(&T)) -> &Self
+    where
+        Self: for<'a> Value<'a, &'a T>,
+    {
+        if let Some(value) = self.value() {
+            f(value);
+        }
+        self
+    }
+
+    /// Get the held value if it is a `T` and `predicate` returns `true` for it
+    fn filter_value<T>(&self, predicate: impl FnOnce(&T) -> bool) -> Option<&T>
+    where
+        Self: for<'a> Value<'a, &'a T>,
+    {
+        self.value().filter(|value| predicate(value))
+    }
+
+    /// Like [`IntoValue::into_value`], with an error naming the expected and the held payload
+    /// types, so it keeps its context when bubbled up with `?`
+    fn try_into_value<T>(self) -> Result<T, TypeMismatch<Self>>
+    where
+        Self: IntoValue<T> + Sized,
+    {
+        self.into_value().map_err(|value| TypeMismatch {
+            expected: std::any::type_name::<T>(),
+            found: value.payload_type_name(),
+            value,
+        })
+    }
+
+    /// Get a reference to the held value, panicking with the expected and the held payload types
+    /// if it is not a `T`
+    #[track_caller]
+    fn expect_value<T>(&self) -> &T
+    where
+        Self: for<'a> Value<'a, &'a T>,
+    {
+        match self.value() {
+            Some(value) => value,
+            None => panic!(
+                "expected a `{}` payload, found variant `{}` holding a `{}`",
+                std::any::type_name::<T>(),
+                self.variant_name(),
+                self.payload_type_name()
+            ),
+        }
+    }
+
+    /// Get a clone of the held value if it is a `T`, leaving the enum as it is
+    fn value_cloned<T: Clone>(&self) -> Option<T>
+    where
+        Self: for<'a> Value<'a, &'a T>,
+    {
+        self.value().cloned()
+    }
+
+    /// Call `f` with the held value if it is a `T`, returning its result
+    fn and_then_value<T, R>(&self, f: impl FnOnce(&T) -> Option<R>) -> Option<R>
+    where
+        Self: for<'a> Value<'a, &'a T>,
+    {
+        self.value().and_then(f)
+    }
+
+    /// If the enum holds a `T`, move to the `U` state returned by `f`, failing to compile unless
+    /// the `T -> U` transition was declared with `#[type_enum(transitions(...))]`
+    ///
+    /// If the enum holds another payload, return it unmodified as the error.
+    #[must_use = "the enum is only handed back in the result"]
+    fn transition_to<U, T>(self, f: impl FnOnce(T) -> U) -> Result<Self, Self>
+    where
+        Self: Transition<T, U> + Sized,
+    {
+        self.map_payload(f)
+    }
+
+    /// Get a reference to the held value if it is a `T`, like `value()` with the payload type
+    /// named by turbofish: `e.get::<String>()`
+    fn get<'a, T>(&'a self) -> Option<<Self as Holds<T>>::Ref<'a>>
+    where
+        Self: Holds<T> + Value<'a, <Self as Holds<T>>::Ref<'a>>,
+    {
+        self.value()
+    }
+
+    /// Get a mutable reference to the held value if it is a `T`: `e.get_mut::<String>()`
+    fn get_mut<'a, T>(&'a mut self) -> Option<<Self as Holds<T>>::Mut<'a>>
+    where
+        Self: Holds<T> + ValueMut<'a, <Self as Holds<T>>::Mut<'a>>,
+    {
+        self.value_mut()
+    }
+
+    /// Unwrap the held value if it is a `T`, or return the enum unmodified: `e.take::<String>()`
+    #[must_use = "dropping the result also drops the enum handed back in `Err`"]
+    fn take<T>(self) -> Result<T, Self>
+    where
+        Self: IntoValue<T> + Sized,
+    {
+        self.into_value()
+    }
+}
+
+/// Implemented by the derive for every payload type of the enum `E`
+///
+/// Lets generic code start from the payload: `fn store<P: Payload<MyEnum>>(p: P)` accepts any
+/// payload type of `MyEnum`, and `P::KIND` tells which variant it maps to at compile time.
+pub trait Payload<E: TypeEnum>: Into<E> {
+    /// The kind of the variant holding this payload type
+    const KIND: E::Kind;
+}
+
+/// Implemented by the derive for every payload type `T` of the enum, naming the references
+/// [`TypeEnum::get`] and [`TypeEnum::get_mut`] return
+///
+/// For single field variants like `Variant(String)`, `Ref<'a>` is `&'a String`
+/// For multi-field variants like `Variant(u8, u8)`, `Ref<'a>` is `(&'a u8, &'a u8)`
+pub trait Holds<T> {
+    /// The shared borrow of the payload, as returned by [`Value`]
+    type Ref<'a>
+    where
+        Self: 'a;
+
+    /// The mutable borrow of the payload, as returned by [`ValueMut`]
+    type Mut<'a>
+    where
+        Self: 'a;
+}
+
+/// Implemented by the derive on the enum for every payload type `T`
+///
+/// The reverse of [`Payload`], as a bound on the enum: `fn log_text<E: Contains<String>>(e: &E)`
+/// accepts any enum with a `String` variant, even one whose `From<String>` is left out.
+pub trait Contains<T>: TypeEnum {}
+
+/// Implemented for each transition from a `T` to a `U` payload declared with
+/// `#[type_enum(transitions(...))]`
+pub trait Transition<T, U>: IntoValue<T> + From<U> {}
+
 /// Trait for extracting immutable references from enum variants
 ///
 /// For single field variants like `Variant(String)`, implement `Value<'a, &'a String>`
@@ -26,22 +260,513 @@ pub trait ValueMut<'a, T> {
 pub trait IntoValue<T> {
     /// If the enum is holding a value of type T, unwrap the enum and return the value.
     /// If not, return the enum unmodified
+    #[must_use = "dropping the result also drops the enum handed back in `Err`"]
     fn into_value(self) -> Result<T, Self>
     where
         Self: Sized;
 }
 
-#[cfg(test)]
+/// Trait for cloning the shared pointer out of a variant marked `#[type_enum(shared)]`
+///
+/// For a variant like `Variant(Arc<String>)`, implement `SharedValue<Arc<String>>`
+pub trait SharedValue<P> {
+    /// If the enum is holding a pointer of type P, return a clone of it.
+    fn value_shared(&self) -> Option<P>;
+}
+
+/// Error returned by operators generated with `#[type_enum(delegate_ops(...))]` when the operands
+/// hold different variants, or a variant excluded with `#[type_enum(no_ops)]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpError<K> {
+    /// Name of the operator trait, like `"Add"`
+    pub op: &'static str,
+    /// Kind of the left-hand operand
+    pub lhs: K,
+    /// Kind of the right-hand operand
+    pub rhs: K,
+}
+
+impl<K: std::fmt::Debug> std::fmt::Display for OpError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot apply `{}` to `{:?}` and `{:?}`",
+            self.op, self.lhs, self.rhs
+        )
+    }
+}
+
+impl<K: std::fmt::Debug> std::error::Error for OpError<K> {}
+
+/// Error returned by `TryFrom` impls generated with `#[type_enum(try_from(...))]` when the source
+/// value doesn't convert into the variant's payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TryFromError<K, E> {
+    /// Kind of the variant the value was converted for
+    pub kind: K,
+    /// Error of the payload type's own `TryFrom` impl
+    pub source: E,
+}
+
+impl<K: std::fmt::Debug, E: std::fmt::Display> std::fmt::Display for TryFromError<K, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot convert into `{:?}`: {}", self.kind, self.source)
+    }
+}
+
+impl<K: std::fmt::Debug, E: std::error::Error + 'static> std::error::Error for TryFromError<K, E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Error returned by [`TypeEnum::try_into_value`] when the enum holds another payload type
+///
+/// Carries the enum back, along with both type names for the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeMismatch<E> {
+    /// Name of the payload type asked for, as given by [`std::any::type_name`]
+    pub expected: &'static str,
+    /// Name of the payload type held, as given by [`TypeEnum::payload_type_name`]
+    pub found: &'static str,
+    /// The enum, unmodified
+    pub value: E,
+}
+
+impl<E> TypeMismatch<E> {
+    /// Get the enum back
+    pub fn into_inner(self) -> E {
+        self.value
+    }
+}
+
+impl<E> std::fmt::Display for TypeMismatch<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected a `{}` payload, found a `{}`",
+            self.expected, self.found
+        )
+    }
+}
+
+impl<E: std::fmt::Debug> std::error::Error for TypeMismatch<E> {}
+
+/// Which value [`dedup_by_type`] keeps when several hold the same payload type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    /// Keep the earliest value, like "first setting wins"
+    First,
+    /// Keep the latest value, like "last setting wins"
+    Last,
+}
+
+/// Remove values holding the same payload type as another one, keeping the first or last of each
+///
+/// The kept values stay in their original relative order. Values of one variant count as the same
+/// payload type, so skipped variants are deduplicated too.
+pub fn dedup_by_type<E>(values: &mut Vec<E>, keep: Keep) {
+    fn keep_first<E>(values: &mut Vec<E>) {
+        let mut seen = Vec::new();
+        values.retain(|value| {
+            let discriminant = std::mem::discriminant(value);
+            if seen.contains(&discriminant) {
+                false
+            } else {
+                seen.push(discriminant);
+                true
+            }
+        });
+    }
+
+    match keep {
+        Keep::First => keep_first(values),
+        Keep::Last => {
+            values.reverse();
+            keep_first(values);
+            values.reverse();
+        }
+    }
+}
+
+/// Sort values by variant, in declaration order
+///
+/// The sort is stable, so values of the same variant stay in their original relative order.
+pub fn sort_by_type<E: TypeEnum>(values: &mut [E]) {
+    values.sort_by_key(TypeEnum::kind);
+}
+
+/// Sort values by variant, ordering the variants by the tag `key` assigns to each kind instead
+/// of their declaration order
+///
+/// Values of variants with the same tag stay in their original relative order.
+pub fn sort_by_type_key<E: TypeEnum, K: Ord>(values: &mut [E], mut key: impl FnMut(E::Kind) -> K) {
+    values.sort_by_key(|value| key(value.kind()));
+}
+
+/// Sort values by variant in declaration order, and values of the same variant by their payload
+///
+/// Uses the enum's own `Ord` for values of the same variant, which compares the payloads when
+/// derived.
+pub fn sort_by_type_then_value<E: TypeEnum + Ord>(values: &mut [E]) {
+    values.sort_by(|a, b| a.kind().cmp(&b.kind()).then_with(|| a.cmp(b)));
+}
+
+/// Build a `Vec` of enum values from a list of payloads
+///
+/// Each element is converted through `Into<E>`, so `values![MyEnum; 1i64, "x".to_string(), (1u8, 2u8)]`
+/// produces a `Vec<MyEnum>` holding a `Number`, a `String` and a `Tuple` variant.
+#[macro_export]
+macro_rules! values {
+    ($enum:ty; $($value:expr),* $(,)?) => {
+        {
+            let values: ::std::vec::Vec<$enum> =
+                ::std::vec![$(::core::convert::Into::<$enum>::into($value)),*];
+            values
+        }
+    };
+}
+
+/// Implement [`Value`], [`ValueMut`] and [`IntoValue`] for the listed single-field variants of an
+/// enum that can't use the derive
+///
+/// `impl_value!(Shape { Circle => f64, Label => String });` implements the traits for `&f64` and
+/// `&String` through `Shape::Circle` and `Shape::Label`. Variants left out are simply not covered.
+#[macro_export]
+macro_rules! impl_value {
+    ($enum:ty { $($variant:ident => $payload:ty),+ $(,)? }) => {
+        $(
+            impl<'a> $crate::Value<'a, &'a $payload> for $enum {
+                fn value(&'a self) -> ::core::option::Option<&'a $payload> {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        Self::$variant(value) => ::core::option::Option::Some(value),
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+
+            impl<'a> $crate::ValueMut<'a, &'a mut $payload> for $enum {
+                fn value_mut(&'a mut self) -> ::core::option::Option<&'a mut $payload> {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        Self::$variant(value) => ::core::option::Option::Some(value),
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+
+            impl $crate::IntoValue<$payload> for $enum {
+                fn into_value(self) -> ::core::result::Result<$payload, Self> {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        Self::$variant(value) => ::core::result::Result::Ok(value),
+                        other => ::core::result::Result::Err(other),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+/// Convert a value of a derived enum by mapping each of its payload types, failing to compile if
+/// a payload type isn't handled
+///
+/// Each arm names a payload type, binds the payload and evaluates to the result:
+///
+/// ```ignore
+/// let message = transform!(Source; value;
+///     i64 => |n| Target::from(n as f64),
+///     String => |s| Target::from(s.len()),
+///     (u8, u8) => |(a, b)| Target::from(u16::from(a) + u16::from(b)),
+/// );
+/// ```
+///
+/// Skipped variants have no payload type of their own, so an enum with skipped variants needs a
+/// trailing `; else |value| ...` arm, which receives the unhandled value and also turns off the
+/// exhaustiveness check.
+#[macro_export]
+macro_rules! transform {
+    (
+        $source:ty; $value:expr;
+        $($payload:ty => |$binding:pat_param| $body:expr),+ $(,)?
+        $(; else |$rest:pat_param| $rest_body:expr $(,)?)?
+    ) => {
+        {
+            let value: $source = $value;
+            match $crate::TypeEnum::kind(&value) {
+                $(
+                    <$payload as $crate::Payload<$source>>::KIND => {
+                        let $binding = match $crate::IntoValue::<$payload>::into_value(value) {
+                            ::core::result::Result::Ok(payload) => payload,
+                            ::core::result::Result::Err(_) => {
+                                ::core::unreachable!("the kind matched this payload type")
+                            }
+                        };
+                        $body
+                    }
+                )+
+                $(
+                    _ => {
+                        let $rest = value;
+                        $rest_body
+                    }
+                )?
+            }
+        }
+    };
+}
+
+/// Declare an enum of single-field variants and implement `From`, [`Value`], [`ValueMut`] and
+/// [`IntoValue`] for it, without the derive or its proc-macro dependencies
+///
+/// Only tuple variants with a single field are accepted, and duplicate payload types are reported
+/// by the compiler as conflicting impls. For anything more, use `#[derive(TypeEnum)]`.
+#[macro_export]
+macro_rules! type_enum_decl {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$($variant_attr:tt)*])* $variant:ident($payload:ty)),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis enum $name {
+            $($(#[$($variant_attr)*])* $variant($payload)),+
+        }
+
+        $(
+            $crate::__cfg_gated! {
+                [$([$($variant_attr)*])*] [];
+
+                impl ::core::convert::From<$payload> for $name {
+                    fn from(value: $payload) -> Self {
+                        $name::$variant(value)
+                    }
+                }
+
+                $crate::impl_value!($name { $variant => $payload });
+            }
+        )+
+    };
+}
+
+/// Emit the items after the `;` gated by the `#[cfg]` attributes among the listed attributes,
+/// for `type_enum_decl!` variants
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cfg_gated {
+    ([] [$($cfg:tt)*]; $($item:tt)*) => {
+        $($cfg)*
+        const _: () = {
+            $($item)*
+        };
+    };
+    ([[cfg $($predicate:tt)*] $($rest:tt)*] [$($cfg:tt)*]; $($item:tt)*) => {
+        $crate::__cfg_gated! { [$($rest)*] [$($cfg)* #[cfg $($predicate)*]]; $($item)* }
+    };
+    ([[$($other:tt)*] $($rest:tt)*] $cfg:tt; $($item:tt)*) => {
+        $crate::__cfg_gated! { [$($rest)*] $cfg; $($item)* }
+    };
+}
+
+/// Fail to compile if two enums have a payload type in common
+///
+/// Crates that take `impl Into<E>` for several enums can assert that no type converts into more
+/// than one of them: `assert_disjoint!(Request, Event);`. The compiler reports a shared type as
+/// "type annotations needed", because its position in the combined payload list is ambiguous.
+#[macro_export]
+macro_rules! assert_disjoint {
+    ($a:ty, $b:ty $(,)?) => {
+        const _: () = {
+            fn distinct<L: $crate::type_list::Distinct<L, I>, I>() {}
+            let _ = distinct::<
+                <<$a as $crate::TypeEnum>::Types as $crate::type_list::Concat<
+                    <$b as $crate::TypeEnum>::Types,
+                >>::Output,
+                _,
+            >;
+        };
+    };
+}
+
+/// Fail to compile unless both enums have each of the listed payload types
+///
+/// `assert_overlap!(Request, Event; u64, String);` is the counterpart of [`assert_disjoint!`] for
+/// enums that are meant to accept the same values.
+#[macro_export]
+macro_rules! assert_overlap {
+    ($a:ty, $b:ty; $($payload:ty),+ $(,)?) => {
+        const _: () = {
+            fn member<L: $crate::type_list::Member<T, I>, T, I>() {}
+            $(
+                let _ = member::<<$a as $crate::TypeEnum>::Types, $payload, _>;
+                let _ = member::<<$b as $crate::TypeEnum>::Types, $payload, _>;
+            )+
+        };
+    };
+}
+
+/// Build a [`DispatchTable`](dispatch::DispatchTable) holding one function per payload type,
+/// failing to compile if a payload type isn't handled
+///
+/// Each arm names a payload type and a `fn(&Payload) -> R`, usually a closure:
+///
+/// ```ignore
+/// static AREA: DispatchTable<Shape, f64> = dispatch_table!(Shape => f64;
+///     Circle => |circle| circle.radius * circle.radius * std::f64::consts::PI,
+///     Square => |square| square.side * square.side,
+/// );
+/// let area = AREA.call(&shape);
+/// ```
+///
+/// Only single-field variants can be listed. As with [`transform!`], enums with skipped variants
+/// need a trailing `; else |value| ...` arm, a `fn(&E) -> R` called for every unlisted variant.
+#[macro_export]
+macro_rules! dispatch_table {
+    ($source:ty => $ret:ty; $($payload:ty => $f:expr),+ $(,)? $(; else $fallback:expr)?) => {{
+        // Exhaustiveness is checked by matching on the kinds, like `transform!`
+        const _: () = {
+            #[allow(unreachable_patterns)]
+            fn exhaustive(kind: <$source as $crate::TypeEnum>::Kind) {
+                match kind {
+                    $(<$payload as $crate::Payload<$source>>::KIND => {})+
+                    $(_ => {
+                        let _: fn(&$source) -> $ret = $fallback;
+                    })?
+                }
+            }
+            let _ = exhaustive;
+        };
+
+        const KINDS: usize = <$source as $crate::TypeEnum>::KINDS.len();
+        #[allow(unused_mut, unused_assignments)]
+        const ENTRIES: [fn(&$source) -> $ret; KINDS] = {
+            let mut missing: fn(&$source) -> $ret = |_| {
+                ::core::unreachable!("every payload type has an entry")
+            };
+            $(missing = $fallback;)?
+            let mut entries = [missing; KINDS];
+            let mut filled = [false; KINDS];
+            $(
+                let index = <$payload as $crate::Payload<$source>>::KIND as usize;
+                ::core::assert!(!filled[index], "payload type listed twice in `dispatch_table!`");
+                filled[index] = true;
+                entries[index] = |value| {
+                    let f: fn(&$payload) -> $ret = $f;
+                    match $crate::Value::<&$payload>::value(value) {
+                        ::core::option::Option::Some(payload) => f(payload),
+                        ::core::option::Option::None => {
+                            ::core::unreachable!("the kind matched this payload type")
+                        }
+                    }
+                };
+            )+
+            entries
+        };
+        $crate::dispatch::DispatchTable::new(&ENTRIES, |value| {
+            $crate::TypeEnum::kind(value) as usize
+        })
+    }};
+}
+
+#[cfg(all(test, feature = "derive"))]
 mod test {
     use super::*;
 
-    #[derive(Debug, PartialEq, TypeEnum)]
+    #[derive(Debug, Clone, PartialEq, TypeEnum)]
     enum ExampleDerive {
         Number(i64),
         String(String),
         Tuple(u8, u8),
     }
 
+    // Generates its own tests in `__type_enum_roundtrip_roundtrip_derive`
+    #[allow(unused)]
+    #[derive(Debug, PartialEq, TypeEnum)]
+    #[type_enum(test_roundtrip)]
+    enum RoundtripDerive {
+        Number(i64),
+        String(String),
+        Tuple(u8, u8),
+        #[type_enum(skip)]
+        Skipped(String),
+        #[type_enum(no_from)]
+        Flag(bool),
+    }
+
+    // Also goes through `serde_json` in `__type_enum_roundtrip_roundtrip_serde`
+    #[allow(unused)]
+    #[derive(Debug, PartialEq, TypeEnum, serde::Serialize, serde::Deserialize)]
+    #[type_enum(test_roundtrip(format = "serde_json"))]
+    enum RoundtripSerde {
+        Number(i64),
+        String(String),
+        Tuple(u8, u8),
+    }
+
+    // Generated code must not trip lints that strict crates enable
+    #[deny(
+        unused_qualifications,
+        clippy::pedantic,
+        clippy::nursery,
+        clippy::same_name_method
+    )]
+    mod strict_lints {
+        use super::*;
+
+        #[allow(unused)]
+        #[derive(Debug, PartialEq, Eq, TypeEnum)]
+        #[type_enum(
+            test_roundtrip,
+            ref_enum,
+            parts,
+            async_visitor,
+            delegate_ops(Add),
+            transitions(Small -> Large)
+        )]
+        pub enum Strict {
+            Small(u8),
+            Large(u64),
+            Pair(u8, u16),
+            #[type_enum(no_ops)]
+            Label(String),
+        }
+    }
+
+    // Generated code handling a deprecated variant must not warn about it
+    #[deny(deprecated)]
+    mod deprecated_variant {
+        use super::*;
+
+        #[allow(unused)]
+        #[derive(TypeEnum)]
+        #[type_enum(ref_enum, async_visitor, parts)]
+        #[cfg_attr(feature = "serde", type_enum(serde))]
+        pub enum Legacy {
+            Current(u8),
+            #[deprecated(note = "use `Current`")]
+            Old(u8, u16),
+        }
+    }
+
+    // Companion items live in `namespaced_types` rather than next to the enum
+    #[derive(TypeEnum)]
+    #[type_enum(ref_enum, module(name = "namespaced_types", vis = "pub(crate)"))]
+    enum Namespaced {
+        Number(i64),
+        Text(String),
+    }
+
+    /// Poll a future that is expected to complete immediately
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        match std::pin::pin!(future).poll(&mut cx) {
+            std::task::Poll::Ready(output) => output,
+            std::task::Poll::Pending => panic!("future was not ready"),
+        }
+    }
+
     #[test]
     fn test_from_conversions() {
         let string: ExampleDerive = "foo".to_string().into();
@@ -145,6 +870,185 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_std_try_from() {
+        fn port(value: ExampleDerive) -> Result<u16, ExampleDerive> {
+            let number: i64 = value.try_into()?;
+            Ok(number as u16)
+        }
+
+        assert_eq!(port(8080i64.into()), Ok(8080));
+        assert_eq!(
+            port("x".to_string().into()),
+            Err(ExampleDerive::String("x".to_string()))
+        );
+        assert_eq!(
+            <(u8, u8)>::try_from(ExampleDerive::from((1u8, 2u8))),
+            Ok((1, 2))
+        );
+        assert_eq!(
+            String::try_from(Wrapper::<u32>::from("y".to_string())),
+            Ok("y".to_string())
+        );
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Wrapper<T> {
+            Items(Vec<T>),
+            Label(String),
+        }
+
+        // `Box<dyn Error>` already converts from any error through std's blanket impls
+        #[derive(Debug, TypeEnum)]
+        enum Failure {
+            Code(u16),
+            Other(Box<dyn std::error::Error + Send + Sync>),
+        }
+
+        impl std::fmt::Display for Failure {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("failure")
+            }
+        }
+
+        impl std::error::Error for Failure {}
+
+        assert_eq!(u16::try_from(Failure::from(5u16)).ok(), Some(5));
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Failure::from(5u16).into();
+        assert_eq!(boxed.to_string(), "failure");
+
+        // `skip(try_from_enum)` leaves the impls out, for payloads converting otherwise
+        #[derive(Debug, TypeEnum)]
+        enum Level {
+            #[type_enum(skip(try_from_enum))]
+            Raw(u8),
+            Name(String),
+        }
+
+        impl From<Level> for u8 {
+            fn from(level: Level) -> u8 {
+                level.into_value().unwrap_or(0)
+            }
+        }
+
+        assert_eq!(u8::from(Level::from(3u8)), 3);
+        assert_eq!(u8::from(Level::from("x".to_string())), 0);
+    }
+
+    #[test]
+    fn test_std_try_from_ref() {
+        fn len(value: &ExampleDerive) -> Result<usize, &ExampleDerive> {
+            let text: &String = value.try_into()?;
+            Ok(text.len())
+        }
+
+        let text = ExampleDerive::from("abc".to_string());
+        let number = ExampleDerive::from(1i64);
+        assert_eq!(len(&text), Ok(3));
+        assert_eq!(len(&number), Err(&number));
+
+        let tuple = ExampleDerive::from((1u8, 2u8));
+        assert_eq!(<(&u8, &u8)>::try_from(&tuple), Ok((&1, &2)));
+    }
+
+    #[test]
+    fn test_turbofish_accessors() {
+        let mut text = ExampleDerive::from("abc".to_string());
+        assert_eq!(text.get::<String>().map(String::len), Some(3));
+        assert_eq!(text.get::<i64>(), None);
+
+        if let Some(text) = text.get_mut::<String>() {
+            text.push('d');
+        }
+        assert_eq!(text.get_mut::<(u8, u8)>(), None);
+        let text = text.take::<i64>().unwrap_err();
+        assert_eq!(text.take::<String>(), Ok("abcd".to_string()));
+
+        let tuple = ExampleDerive::from((1u8, 2u8));
+        assert_eq!(tuple.get::<(u8, u8)>(), Some((&1, &2)));
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Borrowed<'e, T> {
+            Text(&'e str),
+            Items(Vec<T>),
+        }
+        let borrowed = Borrowed::<u8>::from("x");
+        assert_eq!(borrowed.get::<&str>(), Some(&"x"));
+        assert_eq!(borrowed.get::<Vec<u8>>(), None);
+    }
+
+    #[test]
+    fn test_contains() {
+        fn text_len<E>(value: &E) -> Option<usize>
+        where
+            E: Contains<String> + for<'a> Value<'a, &'a String>,
+        {
+            value.value().map(String::len)
+        }
+
+        #[derive(TypeEnum)]
+        enum Log {
+            #[type_enum(no_from)]
+            Line(String),
+            Code(u16),
+        }
+
+        assert_eq!(text_len(&ExampleDerive::from("abc".to_string())), Some(3));
+        assert_eq!(text_len(&Log::Line("ab".to_string())), Some(2));
+        assert_eq!(text_len(&Log::from(7u16)), None);
+    }
+
+    #[test]
+    fn test_value_cloned() {
+        let text = ExampleDerive::from("abc".to_string());
+        assert_eq!(text.value_cloned::<String>(), Some("abc".to_string()));
+        assert_eq!(text.value_cloned::<i64>(), None);
+        let number: Option<i64> = ExampleDerive::from(3i64).value_cloned();
+        assert_eq!(number, Some(3));
+        assert_eq!(text.value(), Some(&"abc".to_string()));
+    }
+
+    #[test]
+    fn test_expect_value() {
+        let number = ExampleDerive::from(3i64);
+        assert_eq!(*number.expect_value::<i64>(), 3);
+        assert_eq!(number.payload_type_name(), "i64");
+        assert_eq!(
+            ExampleDerive::from((1u8, 2u8)).payload_type_name(),
+            "(u8, u8)"
+        );
+
+        let panic = std::panic::catch_unwind(|| {
+            number.expect_value::<String>();
+        })
+        .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("expected a `"));
+        assert!(message.contains("String` payload"));
+        assert!(message.ends_with("found variant `Number` holding a `i64`"));
+    }
+
+    #[test]
+    fn test_try_into_value() {
+        fn number(value: ExampleDerive) -> Result<i64, Box<dyn std::error::Error>> {
+            Ok(value.try_into_value::<i64>()?)
+        }
+
+        assert_eq!(number(ExampleDerive::from(3i64)).unwrap(), 3);
+        assert_eq!(
+            number(ExampleDerive::from((1u8, 2u8)))
+                .unwrap_err()
+                .to_string(),
+            "expected a `i64` payload, found a `(u8, u8)`"
+        );
+
+        let mismatch = ExampleDerive::from(3i64)
+            .try_into_value::<(u8, u8)>()
+            .unwrap_err();
+        assert_eq!(mismatch.expected, "(u8, u8)");
+        assert_eq!(mismatch.found, "i64");
+        assert_eq!(mismatch.into_inner(), ExampleDerive::from(3i64));
+    }
+
     #[test]
     fn test_clean_syntax_with_inference() {
         let text_enum: ExampleDerive = "clean".to_string().into();
@@ -192,6 +1096,2793 @@ mod test {
         assert_eq!(&foo(42u16), "You gave me a number: 42");
     }
 
+    #[test]
+    fn test_values_macro() {
+        let values = values![ExampleDerive; 1i64, "x".to_string(), (1u8, 2u8)];
+        assert_eq!(
+            values,
+            vec![
+                ExampleDerive::Number(1),
+                ExampleDerive::String("x".to_string()),
+                ExampleDerive::Tuple(1, 2),
+            ]
+        );
+
+        // Trailing commas and empty lists are accepted
+        let single = values![ExampleDerive; 7i64,];
+        assert_eq!(single, vec![ExampleDerive::Number(7)]);
+        let empty = values![ExampleDerive;];
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_kind() {
+        let number: ExampleDerive = 1i64.into();
+        let tuple: ExampleDerive = (1u8, 2u8).into();
+        assert_eq!(number.kind(), ExampleDeriveKind::Number);
+        assert_eq!(tuple.kind(), ExampleDeriveKind::Tuple);
+        assert_ne!(number.kind(), tuple.kind());
+    }
+
+    #[test]
+    fn test_payload_kind() {
+        fn store<P: Payload<ExampleDerive>>(payload: P) -> (ExampleDeriveKind, ExampleDerive) {
+            (P::KIND, payload.into())
+        }
+
+        let (kind, value) = store(1i64);
+        assert_eq!(kind, ExampleDeriveKind::Number);
+        assert_eq!(TypeEnum::kind(&value), kind);
+        assert_eq!(
+            <(u8, u8) as Payload<ExampleDerive>>::KIND,
+            ExampleDeriveKind::Tuple
+        );
+    }
+
+    #[test]
+    fn test_companion_derive() {
+        #[derive(TypeEnum)]
+        #[type_enum(companion_derive(Clone, PartialOrd, Ord))]
+        enum Ordered {
+            First(u8),
+            Second(String),
+        }
+
+        // `Clone` is already derived on the kind enum and is not repeated
+        let mut kinds = vec![
+            Ordered::Second("b".to_string()).kind(),
+            Ordered::First(1).kind(),
+        ];
+        kinds.sort();
+        assert_eq!(kinds, vec![OrderedKind::First, OrderedKind::Second]);
+    }
+
+    #[test]
+    fn test_companion_names() {
+        #[derive(TypeEnum)]
+        #[type_enum(kind_name = "SettingTag", ref_name = "SettingView")]
+        enum Setting {
+            Number(i64),
+            Pair(u8, u8),
+        }
+
+        let number: Setting = 3i64.into();
+        let pair: Setting = (1u8, 2u8).into();
+        assert_eq!(number.kind(), SettingTag::Number);
+        assert!(matches!(number.as_ref(), SettingView::Number(&3)));
+        assert!(matches!(pair.as_ref(), SettingView::Pair(&1, &2)));
+
+        let view = number.as_ref();
+        let copy = view;
+        assert!(matches!(
+            (view, copy),
+            (SettingView::Number(&3), SettingView::Number(&3))
+        ));
+    }
+
+    #[test]
+    fn test_mut_enum() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(mut_enum, companion_derive(Clone, Debug))]
+        enum Cell {
+            Number(i64),
+            Span { start: usize, end: usize },
+            Empty,
+        }
+
+        let mut cells = vec![Cell::from(1), Cell::Span { start: 0, end: 2 }, Cell::Empty];
+        for cell in &mut cells {
+            match cell.as_mut() {
+                CellMut::Number(number) => *number += 1,
+                CellMut::Span { end, .. } => *end = 5,
+                CellMut::Empty => {}
+            }
+        }
+        assert_eq!(
+            cells,
+            vec![Cell::from(2), Cell::Span { start: 0, end: 5 }, Cell::Empty]
+        );
+    }
+
+    #[test]
+    fn test_owned_enum() {
+        use std::borrow::Cow;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(owned, companion_derive(Debug, PartialEq))]
+        enum Token<'a> {
+            Word(&'a str),
+            Bytes(&'a [u8]),
+            Quoted { text: Cow<'a, str>, raw: bool },
+            Count(u32),
+            End,
+        }
+
+        let input = String::from("word");
+        let tokens = [
+            Token::from(&input[..]),
+            Token::from(&b"ab"[..]),
+            Token::Quoted {
+                text: Cow::Borrowed("q"),
+                raw: true,
+            },
+            Token::from(3),
+            Token::End,
+        ];
+        let owned: Vec<TokenOwned> = tokens.iter().map(Token::to_owned).collect();
+        drop(input);
+        assert_eq!(owned[0], TokenOwned::Word("word".to_string()));
+        assert_eq!(owned[1], TokenOwned::Bytes(vec![b'a', b'b']));
+        assert_eq!(
+            owned[2],
+            TokenOwned::Quoted {
+                text: "q".to_string(),
+                raw: true
+            }
+        );
+        assert_eq!(owned[3].borrow(), Token::Count(3));
+        assert_eq!(owned[4].borrow(), Token::End);
+        assert_eq!(owned[0].borrow(), Token::Word("word"));
+        assert_eq!(
+            owned[2].borrow(),
+            Token::Quoted {
+                text: Cow::Borrowed("q"),
+                raw: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_owned_std_paths() {
+        use std::path::{Path, PathBuf};
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(owned, companion_derive(Debug, PartialEq))]
+        enum Entry<'a> {
+            File(&'a std::path::Path),
+            Link(&'a std::ffi::OsStr),
+        }
+
+        let path = PathBuf::from("/etc/hosts");
+        let owned = Entry::from(path.as_path()).to_owned();
+        drop(path);
+        assert_eq!(owned, EntryOwned::File("/etc/hosts".into()));
+        assert_eq!(owned.borrow(), Entry::File(Path::new("/etc/hosts")));
+    }
+
+    #[test]
+    fn test_companion_module() {
+        let text: Namespaced = "hi".to_string().into();
+        assert_eq!(text.kind(), namespaced_types::NamespacedKind::Text);
+        assert!(matches!(
+            text.as_ref(),
+            namespaced_types::NamespacedRef::Text(s) if s == "hi"
+        ));
+    }
+
+    #[test]
+    fn test_type_list() {
+        use type_list::{Member, TypeList};
+
+        fn index_of<E, T, I>() -> usize
+        where
+            E: TypeEnum,
+            E::Types: Member<T, I>,
+        {
+            <E::Types as Member<T, I>>::INDEX
+        }
+
+        assert_eq!(<ExampleDerive as TypeEnum>::Types::LEN, 3);
+        assert_eq!(index_of::<ExampleDerive, i64, _>(), 0);
+        assert_eq!(index_of::<ExampleDerive, String, _>(), 1);
+        assert_eq!(index_of::<ExampleDerive, (u8, u8), _>(), 2);
+    }
+
+    #[test]
+    fn test_delegate_ops() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(delegate_ops(Add, Mul))]
+        enum Scalar {
+            Int(i64),
+            Float(f64),
+            Pair(u8, u8),
+            #[type_enum(no_ops)]
+            Text(String),
+        }
+
+        assert_eq!(Scalar::Int(2) + Scalar::Int(3), Ok(Scalar::Int(5)));
+        assert_eq!(
+            Scalar::Float(1.5) * Scalar::Float(2.0),
+            Ok(Scalar::Float(3.0))
+        );
+        assert_eq!(
+            Scalar::Pair(1, 2) + Scalar::Pair(3, 4),
+            Ok(Scalar::Pair(4, 6))
+        );
+
+        let mismatch = Scalar::Int(1) + Scalar::Float(1.0);
+        assert_eq!(
+            mismatch,
+            Err(OpError {
+                op: "Add",
+                lhs: ScalarKind::Int,
+                rhs: ScalarKind::Float,
+            })
+        );
+        assert_eq!(
+            mismatch.unwrap_err().to_string(),
+            "cannot apply `Add` to `Int` and `Float`"
+        );
+
+        let excluded = Scalar::Text("a".to_string()) + Scalar::Text("b".to_string());
+        assert!(excluded.is_err());
+    }
+
+    #[cfg(feature = "tower")]
+    #[test]
+    fn test_router() {
+        use router::{Router, RouterError};
+        use std::convert::Infallible;
+        use std::future::{Ready, ready};
+        use std::task::{Context, Poll, Waker};
+        use tower::Service;
+
+        struct Describe;
+
+        impl Service<i64> for Describe {
+            type Response = String;
+            type Error = Infallible;
+            type Future = Ready<Result<String, Infallible>>;
+
+            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, n: i64) -> Self::Future {
+                ready(Ok(format!("number {n}")))
+            }
+        }
+
+        impl Service<String> for Describe {
+            type Response = String;
+            type Error = Infallible;
+            type Future = Ready<Result<String, Infallible>>;
+
+            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, s: String) -> Self::Future {
+                ready(Ok(format!("string {s}")))
+            }
+        }
+
+        let mut router = Router::<ExampleDerive, String, Infallible>::new()
+            .route::<i64, _>(Describe)
+            .route::<String, _>(Describe);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(matches!(router.poll_ready(&mut cx), Poll::Ready(Ok(()))));
+
+        let number = block_on(router.call(5i64.into()));
+        assert_eq!(number.unwrap(), "number 5");
+        let string = block_on(router.call("x".to_string().into()));
+        assert_eq!(string.unwrap(), "string x");
+        let tuple = block_on(router.call((1u8, 2u8).into()));
+        assert!(matches!(
+            tuple,
+            Err(RouterError::Unrouted(ExampleDerive::Tuple(1, 2)))
+        ));
+    }
+
+    #[test]
+    fn test_parts() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(parts)]
+        enum Message {
+            Text(String),
+            Point(i32, i32),
+            #[type_enum(skip)]
+            Note(String),
+        }
+
+        let (kind, payload) = Message::Point(1, 2).into_parts();
+        assert_eq!(kind, MessageKind::Point);
+        assert_eq!(
+            Message::from_parts(kind, payload).ok(),
+            Some(Message::Point(1, 2))
+        );
+
+        // Skipped variants round-trip too, since the kind tells them apart
+        let (kind, payload) = Message::Note("n".to_string()).into_parts();
+        assert_eq!(kind, MessageKind::Note);
+        assert_eq!(
+            Message::from_parts(kind, payload).ok(),
+            Some(Message::Note("n".to_string()))
+        );
+
+        // A payload of the wrong type is handed back
+        let rejected = Message::from_parts(MessageKind::Text, Box::new(5u8)).unwrap_err();
+        assert_eq!(rejected.downcast_ref::<u8>(), Some(&5));
+    }
+
+    #[test]
+    fn test_map_payload() {
+        let number: ExampleDerive = 42i64.into();
+        let text = number.map_payload::<i64, String>(|n| n.to_string());
+        assert_eq!(text, Ok(ExampleDerive::String("42".to_string())));
+
+        // Holding a different type hands the enum back
+        let tuple: ExampleDerive = (1u8, 2u8).into();
+        assert_eq!(
+            tuple.map_payload::<i64, String>(|n| n.to_string()),
+            Err(ExampleDerive::Tuple(1, 2))
+        );
+
+        let parse = |s: String| s.parse::<i64>();
+        let parsed = ExampleDerive::String("7".to_string()).try_map_payload(parse);
+        assert_eq!(parsed, Ok(Ok(ExampleDerive::Number(7))));
+        let invalid = ExampleDerive::String("x".to_string()).try_map_payload(parse);
+        assert!(matches!(invalid, Ok(Err(_))));
+        let mismatch = ExampleDerive::Number(1).try_map_payload(parse);
+        assert_eq!(mismatch, Err(ExampleDerive::Number(1)));
+    }
+
+    #[test]
+    fn test_probing_combinators() {
+        let number: ExampleDerive = 42i64.into();
+
+        let mut seen = Vec::new();
+        number
+            .inspect_value::<i64>(|n| seen.push(format!("number {n}")))
+            .inspect_value::<String>(|s| seen.push(format!("string {s}")));
+        assert_eq!(seen, vec!["number 42".to_string()]);
+
+        assert_eq!(number.filter_value::<i64>(|n| *n > 40), Some(&42));
+        assert_eq!(number.filter_value::<i64>(|n| *n > 50), None);
+        assert_eq!(number.filter_value::<String>(|_| true), None);
+
+        assert_eq!(
+            number.and_then_value::<i64, _>(|n| n.checked_mul(2)),
+            Some(84)
+        );
+        assert_eq!(
+            number.and_then_value::<String, _>(|s| s.parse::<i64>().ok()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_field_accessors() {
+        let tuple = ExampleDerive::from_tuple(3, 4);
+        assert_eq!(tuple, ExampleDerive::Tuple(3, 4));
+        assert_eq!(tuple.tuple_field_0(), Some(&3));
+        assert_eq!(tuple.tuple_field_1(), Some(&4));
+
+        let number: ExampleDerive = 1i64.into();
+        assert_eq!(number.tuple_field_0(), None);
+    }
+
+    #[test]
+    fn test_visitor() {
+        #[derive(TypeEnum)]
+        #[type_enum(visitor)]
+        enum Shape {
+            Circle(f64),
+            Rect { width: f64, height: f64 },
+        }
+
+        struct Area;
+
+        impl ShapeVisitor for Area {
+            type Output = f64;
+
+            fn visit_circle(&mut self, radius: &f64) -> f64 {
+                3.0 * radius * radius
+            }
+
+            fn visit_rect(&mut self, width: &f64, height: &f64) -> f64 {
+                width * height
+            }
+        }
+
+        assert_eq!(Shape::from(2.0).accept(&mut Area), 12.0);
+        let rect = Shape::Rect {
+            width: 2.0,
+            height: 3.0,
+        };
+        assert_eq!(rect.accept(&mut Area), 6.0);
+    }
+
+    #[test]
+    fn test_partition() {
+        #[derive(TypeEnum)]
+        #[type_enum(partition)]
+        enum Sample {
+            Number(i64),
+            Label(String),
+            Point { x: i32, y: i32 },
+            Gap,
+            Crate(char),
+            Type(bool),
+        }
+
+        let samples = vec![
+            Sample::from(1),
+            Sample::from("a".to_string()),
+            Sample::Gap,
+            Sample::from(2),
+            Sample::Point { x: 3, y: 4 },
+            Sample::Gap,
+        ];
+        let partition = Sample::partition(samples);
+        assert_eq!(partition.number, vec![1, 2]);
+        assert_eq!(partition.label, vec!["a"]);
+        assert_eq!(partition.point, vec![(3, 4)]);
+        assert_eq!(partition.gap.len(), 2);
+        assert!(partition.crate_.is_empty());
+        assert!(partition.r#type.is_empty());
+
+        let mut partition: SamplePartition = [Sample::from(5)].into_iter().collect();
+        partition.extend([Sample::from(6)]);
+        assert_eq!(partition.number, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_subset_of() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Any {
+            Number(i64),
+            Text(String),
+            Pair(u8, u8),
+            Flag(bool),
+            Missing,
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(subset_of(Any))]
+        enum Scalar {
+            Num(i64),
+            Both { left: u8, right: u8 },
+            Missing,
+        }
+
+        assert_eq!(Any::from(Scalar::Num(1)), Any::Number(1));
+        assert_eq!(
+            Any::from(Scalar::Both { left: 1, right: 2 }),
+            Any::Pair(1, 2)
+        );
+        assert_eq!(Any::from(Scalar::Missing), Any::Missing);
+        assert_eq!(Scalar::try_from(Any::Number(2)), Ok(Scalar::Num(2)));
+        assert_eq!(
+            Scalar::try_from(Any::Pair(3, 4)),
+            Ok(Scalar::Both { left: 3, right: 4 })
+        );
+        assert_eq!(Scalar::try_from(Any::Missing), Ok(Scalar::Missing));
+        assert_eq!(Scalar::try_from(Any::Flag(true)), Err(Any::Flag(true)));
+    }
+
+    #[test]
+    fn test_const_constructors() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(const_constructors)]
+        enum Setting {
+            Limit(u32),
+            Enabled(bool),
+            Range(i64, i64),
+            Name(String),
+        }
+
+        const LIMIT: Setting = Setting::from_u32(10);
+        static DEFAULTS: [Setting; 3] = [
+            Setting::from_bool(true),
+            Setting::from_range(0, 5),
+            Setting::Name(String::new()),
+        ];
+
+        assert_eq!(LIMIT, Setting::from(10u32));
+        assert_eq!(DEFAULTS[0], Setting::from(true));
+        assert_eq!(DEFAULTS[1], Setting::from((0i64, 5i64)));
+    }
+
+    #[test]
+    fn test_wrapping_constructors() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Snapshot([u8; 16]);
+
+        #[derive(Debug, TypeEnum)]
+        enum Message {
+            Big(Box<Snapshot>),
+            Shared(Arc<String>),
+            Local(Rc<Vec<u8>>),
+            Error(Box<dyn std::error::Error + Send + Sync>),
+        }
+
+        let big = Message::new_big(Snapshot::default());
+        assert_eq!(
+            Value::<&Box<Snapshot>>::value(&big).map(|b| &**b),
+            Some(&Snapshot::default())
+        );
+        let shared = Message::new_shared("s".to_string());
+        assert_eq!(
+            Value::<&Arc<String>>::value(&shared).map(|s| s.as_str()),
+            Some("s")
+        );
+        assert!(matches!(Message::new_local(vec![1]), Message::Local(local) if *local == [1]));
+    }
+
+    #[test]
+    fn test_handler() {
+        #[derive(TypeEnum)]
+        #[type_enum(handler)]
+        enum Command {
+            Rename(String),
+            Move { x: i32, y: i32 },
+            Quit,
+        }
+
+        #[derive(Default)]
+        struct Log(Vec<String>);
+
+        impl CommandHandler for Log {
+            type Output = bool;
+
+            fn on_rename(&mut self, name: String) -> bool {
+                self.0.push(name);
+                true
+            }
+
+            fn on_move(&mut self, x: i32, y: i32) -> bool {
+                self.0.push(format!("{x},{y}"));
+                true
+            }
+
+            fn on_quit(&mut self) -> bool {
+                false
+            }
+        }
+
+        let mut log = Log::default();
+        assert!(Command::from("a".to_string()).handle(&mut log));
+        assert!(Command::Move { x: 1, y: 2 }.handle(&mut log));
+        assert!(!Command::Quit.handle(&mut log));
+        assert_eq!(log.0, vec!["a", "1,2"]);
+    }
+
+    #[test]
+    fn test_async_visitor() {
+        #[derive(TypeEnum)]
+        #[type_enum(async_visitor)]
+        enum Event {
+            Click(i32, i32),
+            Key(char),
+            #[type_enum(skip)]
+            Paste(String),
+        }
+
+        struct Describe;
+
+        impl EventAsyncVisitor for Describe {
+            type Output = String;
+
+            async fn visit_click(&mut self, x: &i32, y: &i32) -> String {
+                format!("click at {x},{y}")
+            }
+
+            async fn visit_key(&mut self, key: &char) -> String {
+                format!("key {key}")
+            }
+
+            async fn visit_paste(&mut self, text: &String) -> String {
+                format!("paste {text}")
+            }
+        }
+
+        let events = [
+            Event::from_click(1, 2),
+            'k'.into(),
+            Event::Paste("p".to_string()),
+        ];
+        let described: Vec<_> = events
+            .iter()
+            .map(|event| block_on(event.accept_async(&mut Describe)))
+            .collect();
+        assert_eq!(described, vec!["click at 1,2", "key k", "paste p"]);
+    }
+
+    #[test]
+    fn test_type_enum_map() {
+        let mut map = map::TypeEnumMap::<ExampleDerive>::new();
+        assert_eq!(map.insert(1i64), None);
+        assert_eq!(map.insert("a".to_string()), None);
+        assert_eq!(map.insert(2i64), Some(ExampleDerive::Number(1)));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get::<i64>(), Some(&2));
+        map.get_mut::<String>().unwrap().push('b');
+        assert_eq!(map.remove::<String>(), Some("ab".to_string()));
+        assert_eq!(map.remove::<String>(), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_type_enum_map_entry() {
+        let mut map = map::TypeEnumMap::<ExampleDerive>::new();
+        assert!(!map.entry::<String>().is_occupied());
+
+        *map.entry::<i64>().or_insert(10) += 1;
+        *map.entry::<i64>().or_insert(10) += 1;
+        assert_eq!(map.get::<i64>(), Some(&12));
+
+        map.entry::<String>()
+            .and_modify(|s| s.push('!'))
+            .or_insert_with(|| "new".to_string());
+        map.entry::<String>()
+            .and_modify(|s| s.push('!'))
+            .or_default();
+        assert_eq!(map.get::<String>(), Some(&"new!".to_string()));
+
+        assert!(map.entry::<String>().is_occupied());
+    }
+
+    #[cfg(feature = "redis")]
+    #[test]
+    fn test_redis() {
+        use ::redis::{FromRedisValue, ToRedisArgs, Value as RedisValue};
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(redis)]
+        enum Cached {
+            Count(i64),
+            Name(String),
+            Entry(i64, String),
+        }
+
+        assert_eq!(Cached::Count(3).to_redis_args(), vec![b"3".to_vec()]);
+        assert_eq!(
+            Cached::Entry(1, "x".into()).to_redis_args(),
+            vec![b"1".to_vec(), b"x".to_vec()]
+        );
+        assert_eq!(Cached::Entry(1, "x".into()).num_of_args(), 2);
+
+        let count = Cached::from_redis_value(RedisValue::Int(3)).unwrap();
+        assert_eq!(count, Cached::Count(3));
+        let name = Cached::from_redis_value(RedisValue::BulkString(b"abc".to_vec())).unwrap();
+        assert_eq!(name, Cached::Name("abc".into()));
+        let entry = RedisValue::Array(vec![
+            RedisValue::Int(1),
+            RedisValue::BulkString(b"x".to_vec()),
+        ]);
+        assert_eq!(
+            Cached::from_redis_value(entry).unwrap(),
+            Cached::Entry(1, "x".into())
+        );
+        assert!(Cached::from_redis_value(RedisValue::Nil).is_err());
+
+        fn check_name(name: &str) -> Result<(), ()> {
+            if name.is_empty() { Err(()) } else { Ok(()) }
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(redis)]
+        enum Checked {
+            #[type_enum(no_from)]
+            Count(i64),
+            #[type_enum(validate(with = "check_name", error = "()"))]
+            Name(String),
+        }
+
+        let count = Checked::from_redis_value(RedisValue::Int(3)).unwrap();
+        assert_eq!(count, Checked::Count(3));
+        let name = Checked::from_redis_value(RedisValue::BulkString(b"abc".to_vec())).unwrap();
+        assert_eq!(name, Checked::Name("abc".into()));
+        assert!(Checked::from_redis_value(RedisValue::BulkString(Vec::new())).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_tagging() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(serde)]
+        enum Event {
+            Count(u32),
+            Pair(u8, u8),
+            Span {
+                from: u16,
+                to: u16,
+            },
+            Reset,
+            #[type_enum(skip)]
+            Note(u32),
+        }
+
+        let json = serde_json::to_string(&Event::Count(3)).unwrap();
+        assert_eq!(json, r#"{"type":"Count","value":3}"#);
+        let json = serde_json::to_string(&Event::Pair(1, 2)).unwrap();
+        assert_eq!(json, r#"{"type":"Pair","value":[1,2]}"#);
+        let json = serde_json::to_string(&Event::Span { from: 1, to: 2 }).unwrap();
+        assert_eq!(json, r#"{"type":"Span","value":{"from":1,"to":2}}"#);
+        let json = serde_json::to_string(&Event::Reset).unwrap();
+        assert_eq!(json, r#"{"type":"Reset"}"#);
+        let reset: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(reset, Event::Reset);
+        let note: Event = serde_json::from_str(r#"{"type":"Note","value":4}"#).unwrap();
+        assert_eq!(note, Event::Note(4));
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(serde(tag = "kind", content = "data", rename_all = "snake_case"))]
+        enum Setting {
+            MaxRetries(u32),
+            DisplayName(String),
+        }
+
+        let json = serde_json::to_string(&Setting::MaxRetries(3)).unwrap();
+        assert_eq!(json, r#"{"kind":"max_retries","data":3}"#);
+        let name: Setting = serde_json::from_str(r#"{"kind":"display_name","data":"x"}"#).unwrap();
+        assert_eq!(name, Setting::DisplayName("x".to_string()));
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(serde)]
+        enum Message {
+            Text(String),
+            #[type_enum(serde(skip))]
+            Handle(u64),
+        }
+
+        assert_eq!(Message::from(7u64), Message::Handle(7));
+        let err = serde_json::to_string(&Message::Handle(7)).unwrap_err();
+        assert!(err.to_string().contains("`Handle` is excluded"));
+        assert!(serde_json::from_str::<Message>(r#"{"type":"Handle","value":7}"#).is_err());
+        let json = serde_json::to_string(&Message::Text("hi".to_string())).unwrap();
+        assert_eq!(json, r#"{"type":"Text","value":"hi"}"#);
+    }
+
+    #[test]
+    fn test_assert_disjoint() {
+        #[derive(TypeEnum)]
+        enum Request {
+            Id(u64),
+            Name(String),
+            Range(u8, u8),
+        }
+
+        #[derive(TypeEnum)]
+        enum Event {
+            Count(u32),
+            Pair(u16, u16),
+        }
+
+        #[derive(TypeEnum)]
+        enum Lookup {
+            Name(String),
+            Id(u64),
+            Flag(bool),
+        }
+
+        crate::assert_disjoint!(Request, Event);
+        crate::assert_disjoint!(Event, Lookup);
+        crate::assert_overlap!(Request, Lookup; String, u64);
+    }
+
+    #[cfg(feature = "ts-rs")]
+    #[test]
+    fn test_ts() {
+        use ts_rs::{Config, TS};
+
+        #[derive(TypeEnum)]
+        #[type_enum(ts)]
+        enum Shape {
+            Circle(f64),
+            Label(String),
+            Point(i32, i32),
+            Rect {
+                width: u32,
+                height: u32,
+            },
+            #[type_enum(serde(skip))]
+            Hidden(Vec<u8>),
+        }
+
+        assert_eq!(
+            Shape::decl(&Config::new()),
+            "type Shape = { \"type\": \"Circle\", \"value\": number } \
+             | { \"type\": \"Label\", \"value\": string } \
+             | { \"type\": \"Point\", \"value\": [number, number] } \
+             | { \"type\": \"Rect\", \"value\": { width: number, height: number } };"
+        );
+
+        #[derive(TypeEnum)]
+        #[type_enum(ts, serde(tag = "kind", content = "data", rename_all = "snake_case"))]
+        enum Setting {
+            MaxRetries(u32),
+            DisplayName(Option<String>),
+        }
+
+        assert_eq!(
+            Setting::inline(&Config::new()),
+            "{ \"kind\": \"max_retries\", \"data\": number } \
+             | { \"kind\": \"display_name\", \"data\": string | null }"
+        );
+    }
+
+    #[test]
+    fn test_rename_variant() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(getters, is_methods)]
+        enum Field {
+            #[type_enum(rename = "unsigned")]
+            U64(u64),
+            #[type_enum(rename = "json_text")]
+            Json(String),
+        }
+
+        let field = Field::from("{}".to_string());
+        assert_eq!(field.kind(), FieldKind::JsonText);
+        assert!(field.is_json_text());
+        assert_eq!(field.as_json_text(), Some(&"{}".to_string()));
+        assert_eq!(Field::from(1u64).as_unsigned(), Some(&1));
+        assert_eq!(<u64 as Payload<Field>>::KIND, FieldKind::Unsigned);
+        assert_eq!(Field::from(1u64).variant_name(), "U64");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_renamed_variant() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(serde(rename_all = "UPPERCASE"))]
+        enum Field {
+            #[type_enum(rename = "json_text")]
+            Json(String),
+            Number(i64),
+        }
+
+        let json = serde_json::to_string(&Field::from("a".to_string())).unwrap();
+        assert_eq!(json, r#"{"type":"json_text","value":"a"}"#);
+        assert_eq!(
+            serde_json::from_str::<Field>(&json).unwrap(),
+            Field::from("a".to_string())
+        );
+        let number = serde_json::to_string(&Field::from(1)).unwrap();
+        assert_eq!(number, r#"{"type":"NUMBER","value":1}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_validated_variant() {
+        #[derive(Debug)]
+        struct PortError(u16);
+
+        impl std::fmt::Display for PortError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "port {} is reserved", self.0)
+            }
+        }
+
+        fn check_port(port: &u16) -> Result<(), PortError> {
+            if *port >= 1024 {
+                Ok(())
+            } else {
+                Err(PortError(*port))
+            }
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(serde)]
+        enum Endpoint {
+            #[type_enum(validate(with = "check_port", error = "PortError"))]
+            Port(u16),
+            Host(String),
+        }
+
+        assert_eq!(
+            serde_json::from_str::<Endpoint>(r#"{"type":"Port","value":8080}"#).unwrap(),
+            Endpoint::Port(8080)
+        );
+        let error = serde_json::from_str::<Endpoint>(r#"{"type":"Port","value":2}"#).unwrap_err();
+        assert!(error.to_string().starts_with("port 2 is reserved"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_non_ascii_variant() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(serde(rename_all = "camelCase"))]
+        enum Mood {
+            Ärger(String),
+            FreudeGroß(u8),
+        }
+
+        let json = serde_json::to_string(&Mood::from("x".to_string())).unwrap();
+        assert_eq!(json, r#"{"type":"ärger","value":"x"}"#);
+        assert_eq!(
+            serde_json::from_str::<Mood>(&json).unwrap(),
+            Mood::from("x".to_string())
+        );
+        let joy = serde_json::to_string(&Mood::from(1u8)).unwrap();
+        assert_eq!(joy, r#"{"type":"freudeGroß","value":1}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_ref_enum() {
+        #[derive(TypeEnum)]
+        #[type_enum(serde(rename_all = "lowercase"), ref_enum)]
+        enum Blob {
+            Bytes(Vec<u8>),
+            Pair(String, u32),
+            #[type_enum(serde(skip))]
+            Secret(String),
+        }
+
+        let pair = Blob::Pair("x".to_string(), 2);
+        assert_eq!(
+            serde_json::to_string(&pair.as_ref()).unwrap(),
+            serde_json::to_string(&pair).unwrap()
+        );
+        let bytes = Blob::from(vec![1u8, 2]);
+        assert_eq!(
+            serde_json::to_string(&bytes.as_ref()).unwrap(),
+            r#"{"type":"bytes","value":[1,2]}"#
+        );
+        let secret = Blob::Secret("hunter2".to_string());
+        assert!(serde_json::to_string(&secret.as_ref()).is_err());
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn test_telemetry() {
+        use telemetry::{CounterSnapshot, Telemetry};
+
+        #[derive(TypeEnum)]
+        #[type_enum(telemetry)]
+        enum Metric {
+            Count(u64),
+            Label(String),
+            #[type_enum(skip)]
+            Other(()),
+        }
+
+        let count = Metric::from(3u64);
+        let _ = Metric::from(4u64);
+        let _ = Metric::Other(());
+        assert_eq!(Value::<&String>::value(&count), None);
+        assert!(IntoValue::<String>::into_value(count).is_err());
+
+        assert_eq!(
+            Metric::snapshot(),
+            [
+                (
+                    MetricKind::Count,
+                    CounterSnapshot {
+                        constructed: 2,
+                        failed_extractions: 0
+                    }
+                ),
+                (
+                    MetricKind::Label,
+                    CounterSnapshot {
+                        constructed: 0,
+                        failed_extractions: 2
+                    }
+                ),
+                (MetricKind::Other, CounterSnapshot::default()),
+            ]
+        );
+        Metric::reset();
+        assert_eq!(Metric::counters()[0].snapshot(), CounterSnapshot::default());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_type_enum_map_serde() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, TypeEnum)]
+        enum Setting {
+            Count(u32),
+            Name(String),
+        }
+
+        let mut map = map::TypeEnumMap::<Setting>::new();
+        map.insert(3u32);
+        map.insert("x".to_string());
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, r#"[{"Count":3},{"Name":"x"}]"#);
+
+        // Later values of the same variant win
+        let map: map::TypeEnumMap<Setting> =
+            serde_json::from_str(r#"[{"Count":1},{"Name":"y"},{"Count":2}]"#).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get::<u32>(), Some(&2));
+        assert_eq!(map.get::<String>(), Some(&"y".to_string()));
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn test_actix() {
+        use actix::{Actor, Context, Handler};
+
+        struct Ping;
+        struct Add(u32);
+
+        #[derive(TypeEnum)]
+        #[type_enum(actix(result = "u32"))]
+        enum Command {
+            Ping(Ping),
+            Add(Add),
+        }
+
+        struct Counter(u32);
+
+        impl Actor for Counter {
+            type Context = Context<Self>;
+        }
+
+        impl Handler<Ping> for Counter {
+            type Result = u32;
+
+            fn handle(&mut self, _: Ping, _: &mut Context<Self>) -> u32 {
+                self.0
+            }
+        }
+
+        impl Handler<Add> for Counter {
+            type Result = u32;
+
+            fn handle(&mut self, Add(n): Add, _: &mut Context<Self>) -> u32 {
+                self.0 += n;
+                self.0
+            }
+        }
+
+        impl Handler<Command> for Counter {
+            type Result = u32;
+
+            fn handle(&mut self, command: Command, ctx: &mut Context<Self>) -> u32 {
+                command.dispatch(self, ctx)
+            }
+        }
+
+        actix::System::new().block_on(async {
+            let counter = Counter(1).start();
+            assert_eq!(counter.send(Command::from(Add(2))).await.unwrap(), 3);
+            assert_eq!(counter.send(Command::from(Ping)).await.unwrap(), 3);
+            // Payloads can still be sent on their own
+            assert_eq!(counter.send(Add(1)).await.unwrap(), 4);
+        });
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn test_bson() {
+        use ::bson::Bson;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(bson)]
+        enum Field {
+            Count(i64),
+            Label(String),
+            Point(f64, f64),
+            #[type_enum(skip)]
+            Note(String),
+        }
+
+        assert_eq!(Bson::from(Field::Count(3)), Bson::Int64(3));
+        assert_eq!(
+            Bson::from(Field::Point(1.0, 2.0)),
+            Bson::Array(vec![Bson::Double(1.0), Bson::Double(2.0)])
+        );
+        assert_eq!(
+            Bson::from(Field::Note("n".into())),
+            Bson::String("n".into())
+        );
+
+        assert_eq!(Field::try_from(Bson::Int64(3)), Ok(Field::Count(3)));
+        assert_eq!(
+            Field::try_from(Bson::String("x".into())),
+            Ok(Field::Label("x".into()))
+        );
+        assert_eq!(
+            Field::try_from(Bson::Array(vec![Bson::Double(1.0), Bson::Double(2.0)])),
+            Ok(Field::Point(1.0, 2.0))
+        );
+        // Strict matching only accepts the exact BSON type
+        assert_eq!(Field::try_from(Bson::Int32(3)), Err(Bson::Int32(3)));
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(bson)]
+        enum Doc {
+            User { id: i64, name: String },
+            Tagged { r#type: String },
+        }
+
+        let user = Doc::User {
+            id: 1,
+            name: "a".into(),
+        };
+        let bson = Bson::from(Doc::from((1i64, "a".to_string())));
+        assert_eq!(Doc::try_from(bson), Ok(user));
+        let tagged = Bson::from(Doc::from("t".to_string()));
+        assert_eq!(tagged, Bson::Document(::bson::doc! { "type": "t" }));
+        assert_eq!(
+            Doc::try_from(tagged),
+            Ok(Doc::Tagged { r#type: "t".into() })
+        );
+        // Documents with other keys or extra fields don't match
+        let extra = Bson::Document(::bson::doc! { "id": 1i64, "name": "a", "x": 0 });
+        assert_eq!(Doc::try_from(extra.clone()), Err(extra));
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(bson(coerce))]
+        enum Coerced {
+            Count(i64),
+            Ratio(f64),
+        }
+
+        // Exact matches win over coercions, whatever the declaration order
+        assert_eq!(
+            Coerced::try_from(Bson::Double(2.0)),
+            Ok(Coerced::Ratio(2.0))
+        );
+        assert_eq!(Coerced::try_from(Bson::Int32(3)), Ok(Coerced::Count(3)));
+        assert_eq!(Coerced::try_from(Bson::Null), Err(Bson::Null));
+
+        fn check_port(port: &i32) -> Result<(), ()> {
+            if *port >= 1024 { Ok(()) } else { Err(()) }
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(bson)]
+        enum Checked {
+            #[type_enum(no_from)]
+            Flag(bool),
+            #[type_enum(validate(with = "check_port", error = "()"))]
+            Port(i32),
+        }
+
+        assert_eq!(
+            Checked::try_from(Bson::Boolean(true)),
+            Ok(Checked::Flag(true))
+        );
+        assert_eq!(
+            Checked::try_from(Bson::Int32(8080)),
+            Ok(Checked::Port(8080))
+        );
+        assert_eq!(Checked::try_from(Bson::Int32(80)), Err(Bson::Int32(80)));
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_otel() {
+        use opentelemetry::Value;
+
+        #[derive(TypeEnum)]
+        #[type_enum(otel)]
+        enum Attribute {
+            Count(i64),
+            Ratio(f64),
+            Name(String),
+            Port(u16),
+            Pair(bool, u16),
+        }
+
+        assert_eq!(Value::from(Attribute::Count(3)), Value::I64(3));
+        assert_eq!(Value::from(Attribute::Ratio(0.5)), Value::F64(0.5));
+        assert_eq!(Value::from(Attribute::Name("x".into())), Value::from("x"));
+        // No `From<u16> for Value`, so the `Display` output is recorded
+        assert_eq!(Value::from(Attribute::Port(80)), Value::from("80"));
+        assert_eq!(
+            Value::from(Attribute::Pair(true, 80)),
+            Value::from("(true, 80)")
+        );
+    }
+
+    #[test]
+    fn test_transitions() {
+        #[derive(Debug, PartialEq)]
+        struct Idle;
+        #[derive(Debug, PartialEq)]
+        struct Running(u32);
+        #[derive(Debug, PartialEq)]
+        struct Done;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(transitions(Idle -> Running -> Done, Running -> Idle))]
+        enum Job {
+            Idle(Idle),
+            Running(Running),
+            Done(Done),
+        }
+
+        let job = Job::from(Idle);
+        assert!(job.can_transition_to(JobKind::Running));
+        assert!(!job.can_transition_to(JobKind::Done));
+
+        let job = job.transition_to(|Idle| Running(1)).unwrap();
+        assert_eq!(job, Job::Running(Running(1)));
+        // The transition is declared, but the job doesn't hold an `Idle`
+        let job = job
+            .transition_to::<Running, _>(|Idle| Running(2))
+            .unwrap_err();
+        let job = job.transition_to(|_: Running| Done).unwrap();
+        assert_eq!(job, Job::Done(Done));
+
+        assert_eq!(
+            Job::TRANSITIONS,
+            &[
+                (JobKind::Idle, JobKind::Running),
+                (JobKind::Running, JobKind::Done),
+                (JobKind::Running, JobKind::Idle),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_box_over() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(auto_box_over = 16)]
+        enum Message {
+            Small(u32),
+            Large(Box<[u8; 128]>),
+        }
+
+        // The boxed payload converts from and to its unboxed type
+        let mut message = Message::from([1u8; 128]);
+        assert_eq!(message, Message::Large(Box::new([1; 128])));
+        let large: Option<&[u8; 128]> = message.value();
+        assert_eq!(large.map(|bytes| bytes[0]), Some(1));
+        if let Some(bytes) = ValueMut::<&mut [u8; 128]>::value_mut(&mut message) {
+            bytes[0] = 2;
+        }
+        let bytes: [u8; 128] = message.into_value().unwrap();
+        assert_eq!(bytes[0], 2);
+        assert_eq!(std::mem::size_of::<Message>(), 16);
+    }
+
+    #[test]
+    fn test_dedup_by_type() {
+        let options =
+            values![ExampleDerive; 1i64, "a".to_string(), 2i64, (1u8, 2u8), "b".to_string()];
+
+        let mut first = options.clone();
+        dedup_by_type(&mut first, Keep::First);
+        assert_eq!(
+            first,
+            values![ExampleDerive; 1i64, "a".to_string(), (1u8, 2u8)]
+        );
+
+        let mut last = options;
+        dedup_by_type(&mut last, Keep::Last);
+        assert_eq!(
+            last,
+            values![ExampleDerive; 2i64, (1u8, 2u8), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_schema_hash() {
+        mod v1 {
+            #[derive(crate::TypeEnum)]
+            pub enum Message {
+                Text(String),
+                Code(u32),
+            }
+        }
+
+        mod v1_copy {
+            #[derive(crate::TypeEnum)]
+            pub enum Message {
+                Text(String),
+                Code(u32),
+            }
+        }
+
+        mod v2 {
+            #[derive(crate::TypeEnum)]
+            pub enum Message {
+                Text(String),
+                Code(u64),
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        mod v2_renamed {
+            #[derive(crate::TypeEnum)]
+            #[type_enum(serde(rename_all = "lowercase"))]
+            pub enum Message {
+                Text(String),
+                Code(u64),
+            }
+        }
+
+        mod tagged {
+            #[derive(crate::TypeEnum)]
+            pub enum Message {
+                #[type_enum(tag = 1)]
+                Text(String),
+                #[type_enum(tag = 2)]
+                Code(u32),
+            }
+        }
+
+        mod retagged {
+            #[derive(crate::TypeEnum)]
+            pub enum Message {
+                #[type_enum(tag = 7)]
+                Text(String),
+                #[type_enum(tag = 9)]
+                Code(u32),
+            }
+        }
+
+        const V1: u64 = <v1::Message as TypeEnum>::SCHEMA_HASH;
+        assert_eq!(V1, <v1_copy::Message as TypeEnum>::SCHEMA_HASH);
+        assert_ne!(V1, <v2::Message as TypeEnum>::SCHEMA_HASH);
+        assert_ne!(V1, <tagged::Message as TypeEnum>::SCHEMA_HASH);
+        assert_ne!(
+            <tagged::Message as TypeEnum>::SCHEMA_HASH,
+            <retagged::Message as TypeEnum>::SCHEMA_HASH
+        );
+        #[cfg(feature = "serde")]
+        assert_ne!(
+            <v2::Message as TypeEnum>::SCHEMA_HASH,
+            <v2_renamed::Message as TypeEnum>::SCHEMA_HASH
+        );
+    }
+
+    #[test]
+    fn test_payload_cmp() {
+        #[derive(TypeEnum)]
+        #[type_enum(payload_cmp)]
+        enum Metric {
+            Gauge(f64),
+            Name(String),
+            Range(u8, u8),
+            #[type_enum(no_cmp)]
+            Tags(std::collections::HashMap<String, String>),
+        }
+
+        let gauge = Metric::from(7.5);
+        assert!(gauge > 5.0);
+        assert!(gauge == 7.5);
+        assert!(gauge != "x".to_string());
+        assert_eq!(gauge.partial_cmp(&"x".to_string()), None);
+        let range = Metric::from((1u8, 2u8));
+        assert!(range < (1u8, 3u8));
+        let tags = Metric::from(std::collections::HashMap::new());
+        assert!(tags != 0.0);
+        assert!(5.0 < gauge);
+        assert!((1u8, 2u8) == range);
+    }
+
+    #[test]
+    fn test_delegate_ord() {
+        use std::collections::{BTreeSet, HashSet};
+
+        #[derive(Debug, PartialEq, Eq, TypeEnum)]
+        #[type_enum(delegate_ord)]
+        enum Job {
+            Urgent(u32),
+            Batch(String, u8),
+            Idle,
+        }
+
+        let jobs: BTreeSet<Job> = [
+            Job::Idle,
+            Job::from(("b".to_string(), 1)),
+            Job::from(7),
+            Job::from(("a".to_string(), 2)),
+            Job::from(3),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            jobs.into_iter().collect::<Vec<_>>(),
+            [
+                Job::Urgent(3),
+                Job::Urgent(7),
+                Job::Batch("a".to_string(), 2),
+                Job::Batch("b".to_string(), 1),
+                Job::Idle,
+            ]
+        );
+
+        #[derive(Debug, PartialEq, Eq, TypeEnum)]
+        #[type_enum(delegate_ord)]
+        enum Frame {
+            #[type_enum(tag = 9)]
+            Data(u8),
+            #[type_enum(tag = 1)]
+            Ping(u64),
+        }
+
+        assert!(Frame::Ping(5) < Frame::Data(0));
+        let seen: HashSet<Frame> = [Frame::Ping(1), Frame::Ping(1), Frame::Data(1)].into();
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn test_payload_eq() {
+        #[derive(Debug, TypeEnum)]
+        #[type_enum(payload_eq)]
+        enum Value {
+            Number(i64),
+            Tags(std::collections::HashSet<String>),
+        }
+
+        let number = Value::from(42);
+        assert_eq!(number, 42i64);
+        assert_eq!(42i64, number);
+        assert_ne!(number, std::collections::HashSet::new());
+        assert_ne!(std::collections::HashSet::new(), number);
+    }
+
+    #[test]
+    fn test_kind_description() {
+        #[derive(TypeEnum)]
+        enum Input {
+            #[type_enum(doc = "a port number")]
+            Port(u16),
+            #[type_enum(doc = "a host name or address")]
+            Host(String),
+            Flag(bool),
+        }
+
+        assert_eq!(
+            Input::from(80u16).kind().description(),
+            Some("a port number")
+        );
+        assert_eq!(
+            InputKind::Host.description(),
+            Some("a host name or address")
+        );
+        assert_eq!(InputKind::Flag.description(), None);
+    }
+
+    #[test]
+    fn test_generic_enum() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(parts)]
+        enum Wrapper<T: Clone>
+        where
+            T: Default,
+        {
+            Items(Vec<T>),
+            Label(String),
+            Pair(Option<T>, u8),
+        }
+
+        let items = Wrapper::<u32>::from(vec![1, 2]);
+        assert_eq!(items.kind(), WrapperKind::Items);
+        assert_eq!(items.value(), Some(&vec![1, 2]));
+        assert_eq!(Value::<&String>::value(&items), None);
+
+        let mut label = Wrapper::<u32>::from("x".to_string());
+        if let Some(label) = ValueMut::<&mut String>::value_mut(&mut label) {
+            label.push('y');
+        }
+        assert_eq!(label.into_value(), Ok("xy".to_string()));
+
+        let pair = Wrapper::from((Some(3u64), 1));
+        assert_eq!(pair.pair_field_0(), Some(&Some(3)));
+        assert_eq!(
+            <Vec<u64> as Payload<Wrapper<u64>>>::KIND,
+            WrapperKind::Items
+        );
+        assert_eq!(
+            <<Wrapper<u64> as TypeEnum>::Types as type_list::TypeList>::LEN,
+            3
+        );
+
+        let (kind, payload) = pair.into_parts();
+        assert_eq!(
+            Wrapper::from_parts(kind, payload).unwrap(),
+            Wrapper::Pair(Some(3u64), 1)
+        );
+    }
+
+    #[test]
+    fn test_where_clause() {
+        // The payloads only exist under the `where` bounds, so every impl has to repeat them
+        #[derive(Debug, TypeEnum)]
+        #[type_enum(parts)]
+        enum Collected<I>
+        where
+            I: Iterator,
+            I::Item: Ord,
+        {
+            Items(Vec<I::Item>),
+            Largest(Option<I::Item>, usize),
+            Label(String),
+        }
+
+        type Numbers = std::vec::IntoIter<u8>;
+        let items = Collected::<Numbers>::from(vec![3, 1]);
+        assert_eq!(Value::<&Vec<u8>>::value(&items), Some(&vec![3, 1]));
+        assert_eq!(items.kind(), CollectedKind::Items);
+        let largest = Collected::<Numbers>::from((Some(3), 2));
+        assert_eq!(largest.largest_field_1(), Some(&2));
+        assert_eq!(
+            IntoValue::<(Option<u8>, usize)>::into_value(largest).ok(),
+            Some((Some(3), 2))
+        );
+        let (kind, payload) = Collected::<Numbers>::from("x".to_string()).into_parts();
+        assert_eq!(kind, CollectedKind::Label);
+        assert!(Collected::<Numbers>::from_parts(CollectedKind::Items, payload).is_err());
+    }
+
+    #[test]
+    fn test_boxed_variant() {
+        #[derive(Debug, Default, PartialEq)]
+        struct Huge([u64; 32]);
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Message {
+            #[type_enum(boxed)]
+            Big(Box<Huge>),
+            Small(u8),
+        }
+
+        let mut big = Message::from(Huge::default());
+        assert_eq!(big, Message::Big(Box::default()));
+        assert_eq!(Value::<&Huge>::value(&big), Some(&Huge::default()));
+        if let Some(huge) = ValueMut::<&mut Huge>::value_mut(&mut big) {
+            huge.0[0] = 1;
+        }
+        assert_eq!(
+            Value::<&Box<Huge>>::value(&big).map(|huge| huge.0[0]),
+            Some(1)
+        );
+        assert_eq!(
+            IntoValue::<Huge>::into_value(big).map(|huge| huge.0[0]),
+            Ok(1)
+        );
+        assert_eq!(<Huge as Payload<Message>>::KIND, MessageKind::Big);
+        assert_eq!(
+            IntoValue::<Huge>::into_value(Message::from(2)),
+            Err(Message::Small(2))
+        );
+    }
+
+    #[test]
+    fn test_shared_variant() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Message {
+            #[type_enum(shared)]
+            Text(Arc<String>),
+            #[type_enum(shared)]
+            Local(Rc<Vec<u8>>),
+            Count(u8),
+        }
+
+        let text = Message::from("hi".to_string());
+        assert_eq!(text, Message::Text(Arc::new("hi".to_string())));
+        assert_eq!(Value::<&String>::value(&text), Some(&"hi".to_string()));
+        let shared: Arc<String> = text.value_shared().unwrap();
+        assert_eq!(Arc::strong_count(&shared), 2);
+        assert_eq!(<String as Payload<Message>>::KIND, MessageKind::Text);
+
+        let local = Message::from(vec![1u8]);
+        assert_eq!(Value::<&Vec<u8>>::value(&local), Some(&vec![1]));
+        assert_eq!(
+            SharedValue::<Rc<Vec<u8>>>::value_shared(&local),
+            Some(Rc::new(vec![1]))
+        );
+        assert_eq!(SharedValue::<Arc<String>>::value_shared(&local), None);
+    }
+
+    #[test]
+    fn test_cow_variants() {
+        use std::borrow::Cow;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Field<'a> {
+            Text(Cow<'a, str>),
+            Bytes(Cow<'a, [u8]>),
+            Count(usize),
+        }
+
+        let text = String::from("borrowed");
+        let borrowed = Field::from(text.as_str());
+        assert_eq!(borrowed, Field::Text(Cow::Borrowed("borrowed")));
+        assert_eq!(Value::<&str>::value(&borrowed), Some("borrowed"));
+        let owned = Field::from("owned".to_string());
+        assert_eq!(owned, Field::Text(Cow::Owned("owned".to_string())));
+        assert_eq!(IntoValue::<String>::into_value(borrowed), Ok(text.clone()));
+        assert_eq!(<String as Payload<Field<'_>>>::KIND, FieldKind::Text);
+
+        let bytes = Field::from(vec![1u8, 2]);
+        assert_eq!(Value::<&[u8]>::value(&bytes), Some(&[1, 2][..]));
+        assert_eq!(Field::from(&[3u8][..]), Field::Bytes(Cow::Borrowed(&[3])));
+        assert_eq!(IntoValue::<Vec<u8>>::into_value(bytes), Ok(vec![1, 2]));
+        assert!(matches!(
+            Value::<&Cow<'_, str>>::value(&owned),
+            Some(Cow::Owned(_))
+        ));
+
+        // A variant holding the owned type keeps its conversion
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Label<'a> {
+            Any(Cow<'a, str>),
+            Owned(String),
+        }
+
+        assert_eq!(Label::from("x".to_string()), Label::Owned("x".to_string()));
+        assert_eq!(Label::from("x"), Label::Any(Cow::Borrowed("x")));
+
+        #[derive(Debug, PartialEq, Eq)]
+        struct Name(str);
+
+        impl ToOwned for Name {
+            type Owned = Box<str>;
+
+            fn to_owned(&self) -> Box<str> {
+                self.0.into()
+            }
+        }
+
+        impl std::borrow::Borrow<Name> for Box<str> {
+            fn borrow(&self) -> &Name {
+                // SAFETY: `Name` is a transparent wrapper around `str`
+                unsafe { &*(&**self as *const str as *const Name) }
+            }
+        }
+
+        // Full std paths are still known, other types keep to `ToOwned::Owned`
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Location<'a> {
+            File(Cow<'a, std::path::Path>),
+            Env(Cow<'static, std::ffi::OsStr>),
+            Named(Cow<'a, Name>),
+        }
+
+        let path = Location::from(std::path::PathBuf::from("/tmp"));
+        assert_eq!(
+            IntoValue::<std::path::PathBuf>::into_value(path),
+            Ok("/tmp".into())
+        );
+        let env = Location::from(std::ffi::OsStr::new("HOME"));
+        assert_eq!(
+            Value::<&std::ffi::OsStr>::value(&env),
+            Some("HOME".as_ref())
+        );
+        assert_eq!(
+            IntoValue::<std::ffi::OsString>::into_value(env),
+            Ok("HOME".into())
+        );
+        let owned: Box<str> = "x".into();
+        let named = Location::Named(Cow::Owned(owned));
+        assert!(matches!(
+            Value::<&Name>::value(&named),
+            Some(Name(name)) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn test_downcast_value() {
+        use std::any::Any;
+        use std::fmt;
+
+        #[derive(Debug, PartialEq)]
+        struct Timeout;
+
+        impl fmt::Display for Timeout {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("timed out")
+            }
+        }
+
+        impl std::error::Error for Timeout {}
+
+        trait Plugin: Any {
+            fn id(&self) -> u32;
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Echo;
+
+        impl Plugin for Echo {
+            fn id(&self) -> u32 {
+                1
+            }
+        }
+
+        #[derive(TypeEnum)]
+        enum Outcome {
+            Done(u32),
+            Err(Box<dyn std::error::Error + Send + Sync>),
+        }
+
+        let err = Outcome::from(Box::new(Timeout) as Box<dyn std::error::Error + Send + Sync>);
+        assert_eq!(err.kind(), OutcomeKind::Err);
+        assert_eq!(err.downcast_value::<Timeout>(), Some(&Timeout));
+        assert_eq!(Outcome::from(3).downcast_value::<Timeout>(), None);
+
+        #[derive(TypeEnum)]
+        enum Extension {
+            #[type_enum(downcast)]
+            Plugin(Box<dyn Plugin>),
+            Extra(Box<dyn Any>),
+        }
+
+        let plugin = Extension::from(Box::new(Echo) as Box<dyn Plugin>);
+        assert_eq!(plugin.downcast_value::<Echo>(), Some(&Echo));
+        let id = Value::<&Box<dyn Plugin>>::value(&plugin).map(|plugin| plugin.id());
+        assert_eq!(id, Some(1));
+
+        let extra = Extension::from(Box::new(7u8) as Box<dyn Any>);
+        assert_eq!(extra.downcast_value::<u8>(), Some(&7));
+        assert_eq!(extra.downcast_value::<Echo>(), None);
+
+        // Trait objects without `Any` as a supertrait are left out of `downcast_value()`
+        #[derive(TypeEnum)]
+        enum Callback {
+            Fmt(Box<dyn fmt::Display>),
+            Cb(Box<dyn Fn() -> u8>),
+        }
+
+        let cb = Callback::from(Box::new(|| 2u8) as Box<dyn Fn() -> u8>);
+        assert_eq!(
+            Value::<&Box<dyn Fn() -> u8>>::value(&cb).map(|cb| cb()),
+            Some(2)
+        );
+        let text = Callback::from(Box::new(1) as Box<dyn fmt::Display>);
+        assert_eq!(
+            Value::<&Box<dyn fmt::Display>>::value(&text).map(|t| t.to_string()),
+            Some("1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cfg_variants() {
+        // The compiler removes disabled variants before the derive sees the enum
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(ref_enum, parts)]
+        enum Config {
+            Name(String),
+            #[cfg(not(test))]
+            Missing(NotAType),
+            #[cfg(test)]
+            Present(u8),
+            #[cfg_attr(test, type_enum(skip))]
+            Other(u8),
+        }
+
+        assert_eq!(
+            Config::KINDS,
+            [ConfigKind::Name, ConfigKind::Present, ConfigKind::Other]
+        );
+        assert_eq!(Config::from(1).kind(), ConfigKind::Present);
+
+        type_enum_decl! {
+            #[derive(Debug, PartialEq)]
+            enum Declared {
+                /// Only built with the feature
+                #[cfg(not(test))]
+                Missing(NotAType),
+                #[cfg(test)]
+                Count(u32),
+                Name(String),
+            }
+        }
+
+        assert_eq!(Declared::from(2), Declared::Count(2));
+        assert_eq!(
+            Declared::from("x".to_string()).into_value(),
+            Ok("x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recursive_variants() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Expr {
+            Num(i64),
+            Neg(Box<Self>),
+            List(Vec<Expr>),
+            Add(Box<Self>, Box<Self>),
+        }
+
+        let neg = Expr::from(Box::new(Expr::from(1)));
+        assert_eq!(neg.kind(), ExprKind::Neg);
+        assert_eq!(
+            Value::<&Box<Expr>>::value(&neg),
+            Some(&Box::new(Expr::Num(1)))
+        );
+        let list = Expr::from(vec![Expr::Num(1), neg]);
+        assert_eq!(IntoValue::<Vec<Expr>>::into_value(list).unwrap().len(), 2);
+        let add = Expr::from((Box::new(Expr::Num(1)), Box::new(Expr::Num(2))));
+        assert_eq!(<Box<Expr> as Payload<Expr>>::KIND, ExprKind::Neg);
+        assert_eq!(add.kind(), ExprKind::Add);
+
+        // `Self` in a generic enum stands for the enum with its parameters
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Tree<T> {
+            Leaf(Vec<T>),
+            Node(Vec<Self>),
+        }
+
+        let tree = Tree::<u8>::from(vec![Tree::from(vec![1])]);
+        assert_eq!(tree.kind(), TreeKind::Node);
+    }
+
+    #[test]
+    fn test_const_generic_enum() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Buf<const N: usize> {
+            Fixed([u8; N]),
+            Dyn(Vec<u8>),
+            Framed([u16; N], u8),
+        }
+
+        let fixed = Buf::from([1, 2, 3]);
+        assert_eq!(fixed.kind(), BufKind::Fixed);
+        assert_eq!(Value::<&[u8; 3]>::value(&fixed), Some(&[1, 2, 3]));
+        assert_eq!(
+            IntoValue::<Vec<u8>>::into_value(Buf::<3>::from(vec![4])),
+            Ok(vec![4])
+        );
+        assert_eq!(<[u8; 2] as Payload<Buf<2>>>::KIND, BufKind::Fixed);
+        assert_eq!(Buf::from(([1u16], 2)).kind(), BufKind::Framed);
+    }
+
+    #[test]
+    fn test_borrowed_payloads() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Token<'a> {
+            Str(&'a str),
+            Bytes(&'a [u8]),
+            Span(&'a str, usize),
+            Count(usize),
+        }
+
+        let text = String::from("hello");
+        let token = Token::from(text.as_str());
+        assert_eq!(token.kind(), TokenKind::Str);
+        assert_eq!(Value::<&&str>::value(&token), Some(&"hello"));
+        assert_eq!(Value::<&&[u8]>::value(&token), None);
+
+        let mut bytes = Token::from(&b"abc"[..]);
+        if let Some(bytes) = ValueMut::<&mut &[u8]>::value_mut(&mut bytes) {
+            *bytes = &bytes[1..];
+        }
+        assert_eq!(IntoValue::<&[u8]>::into_value(bytes), Ok(&b"bc"[..]));
+
+        let span = Token::from((&text[1..], 4));
+        assert_eq!(Value::<(&&str, &usize)>::value(&span), Some((&"ello", &4)));
+        assert_eq!(<&str as Payload<Token<'_>>>::KIND, TokenKind::Str);
+
+        // The accessor lifetime steps aside when the enum already uses `'a`
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Pair<'a, 'b> {
+            Left(&'a str),
+            Right(&'b [u8]),
+        }
+
+        let pair = Pair::from(&b"x"[..]);
+        assert_eq!(Value::<&&[u8]>::value(&pair), Some(&&b"x"[..]));
+    }
+
+    #[test]
+    fn test_discriminant() {
+        const BASE: u8 = 10;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(discriminant)]
+        #[repr(u8)]
+        enum Frame {
+            Ping(u32) = 1,
+            Data(Vec<u8>),
+            Close(String) = BASE,
+            Reset = 20,
+            Error(i32),
+        }
+
+        assert_eq!(Frame::from(0u32).discriminant(), 1u8);
+        assert_eq!(Frame::from(vec![]).discriminant(), 2);
+        assert_eq!(Frame::from(String::new()).discriminant(), 10);
+        assert_eq!(Frame::Reset.discriminant(), 20);
+        assert_eq!(Frame::from(-1).discriminant(), 21);
+        // The kinds keep counting from zero
+        assert_eq!(FrameKind::Close as usize, 2);
+        // A primitive repr puts the discriminant first, where FFI code reads it
+        let close = Frame::from(String::new());
+        let native = unsafe { *(&close as *const Frame).cast::<u8>() };
+        assert_eq!(native, close.discriminant());
+
+        #[derive(TypeEnum)]
+        #[type_enum(discriminant)]
+        #[repr(C, i16)]
+        enum Implicit {
+            First(u8),
+            Second(String),
+        }
+
+        assert_eq!(Implicit::from(1).discriminant(), 0i16);
+        assert_eq!(Implicit::from(String::new()).discriminant(), 1);
+
+        #[derive(TypeEnum)]
+        #[type_enum(discriminant)]
+        #[repr(C)]
+        enum Header {
+            Version(u16),
+            Length(u32),
+        }
+
+        let length: std::ffi::c_int = Header::from(0u32).discriminant();
+        assert_eq!(length, 1);
+
+        // Without the option, the enum's own methods keep their names
+        #[derive(TypeEnum)]
+        #[repr(u8)]
+        enum Opcode {
+            Read(u32) = 1,
+            Flag(bool),
+        }
+
+        impl Opcode {
+            fn discriminant(&self) -> &'static str {
+                "custom"
+            }
+
+            fn from_bool(_: bool) -> Self {
+                Opcode::Read(0)
+            }
+        }
+
+        assert_eq!(Opcode::from(true).discriminant(), "custom");
+        assert!(matches!(Opcode::from_bool(true), Opcode::Read(0)));
+    }
+
+    #[test]
+    fn test_non_exhaustive() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(ref_enum, parts)]
+        #[non_exhaustive]
+        enum Event {
+            Click(u32),
+            Key(char),
+            Unknown,
+        }
+
+        let click = Event::from(3);
+        assert_eq!(click.kind(), EventKind::Click);
+        assert!(matches!(click.as_ref(), EventRef::Click(3)));
+        assert_eq!(Event::Unknown.kind(), EventKind::Unknown);
+        let (kind, payload) = Event::from('k').into_parts();
+        assert_eq!(Event::from_parts(kind, payload).unwrap(), Event::Key('k'));
+    }
+
+    #[test]
+    fn test_primary_variant() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Output {
+            #[type_enum(primary)]
+            Stdout(String),
+            Stderr(String),
+            Code(i32),
+        }
+
+        assert_eq!(
+            Output::from("x".to_string()),
+            Output::Stdout("x".to_string())
+        );
+        let stderr = Output::Stderr("e".to_string());
+        assert_eq!(stderr.kind(), OutputKind::Stderr);
+        assert_eq!(Value::<&String>::value(&stderr), None);
+        assert_eq!(<String as Payload<Output>>::KIND, OutputKind::Stdout);
+        assert_eq!(<<Output as TypeEnum>::Types as type_list::TypeList>::LEN, 2);
+    }
+
+    #[test]
+    fn test_only_families() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(only(from, value))]
+        enum Setting {
+            Retries(u32),
+            Name(String),
+            Window(u16, u16),
+        }
+
+        let setting = Setting::from(3);
+        assert_eq!(setting.kind(), SettingKind::Retries);
+        assert_eq!(Value::<&u32>::value(&setting), Some(&3));
+        assert_eq!(
+            Value::<(&u16, &u16)>::value(&Setting::from((1, 2))),
+            Some((&1, &2))
+        );
+        assert_eq!(<String as Payload<Setting>>::KIND, SettingKind::Name);
+    }
+
+    #[test]
+    fn test_extra_sources() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Arg {
+            #[type_enum(from(&str, char))]
+            Text(String),
+            #[type_enum(from(&[u8]))]
+            Bytes(Vec<u8>),
+            Number(i64),
+        }
+
+        fn arg(value: impl Into<Arg>) -> Arg {
+            value.into()
+        }
+
+        assert_eq!(arg("literal"), Arg::Text("literal".to_string()));
+        assert_eq!(arg('x'), Arg::Text("x".to_string()));
+        assert_eq!(arg(&b"ab"[..]), Arg::Bytes(vec![b'a', b'b']));
+        assert_eq!(arg(3), Arg::Number(3));
+    }
+
+    #[test]
+    fn test_numeric_coercions() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Arg {
+            #[type_enum(coerce(u8, u16, u32, i8, i16, i32))]
+            Number(i64),
+            #[type_enum(coerce(f32))]
+            Float(f64),
+        }
+
+        fn arg(value: impl Into<Arg>) -> Arg {
+            value.into()
+        }
+
+        assert_eq!(arg(3u8), Arg::Number(3));
+        assert_eq!(arg(u32::MAX), Arg::Number(i64::from(u32::MAX)));
+        assert_eq!(arg(-2i16), Arg::Number(-2));
+        assert_eq!(arg(1.5f32), Arg::Float(1.5));
+    }
+
+    #[test]
+    fn test_fallible_sources() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Endpoint {
+            #[type_enum(try_from(i64, u32))]
+            Port(u16),
+            Host(String),
+        }
+
+        assert_eq!(Endpoint::try_from(8080i64), Ok(Endpoint::Port(8080)));
+        let error = Endpoint::try_from(70_000u32).unwrap_err();
+        assert_eq!(error.kind, EndpointKind::Port);
+        assert_eq!(
+            error.to_string(),
+            format!("cannot convert into `Port`: {}", error.source)
+        );
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn test_validated_variant() {
+        #[derive(Debug, PartialEq)]
+        struct PortError(u16);
+
+        fn check_port(port: &u16) -> Result<(), PortError> {
+            if *port >= 1024 {
+                Ok(())
+            } else {
+                Err(PortError(*port))
+            }
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(parts)]
+        enum Endpoint {
+            #[type_enum(validate(with = "check_port", error = "PortError"))]
+            Port(u16),
+            Host(String),
+        }
+
+        assert_eq!(Endpoint::try_from(8080u16), Ok(Endpoint::Port(8080)));
+        assert_eq!(Endpoint::try_new_port(9000), Ok(Endpoint::Port(9000)));
+        let error = Endpoint::try_new_port(80).unwrap_err();
+        assert_eq!(error.kind, EndpointKind::Port);
+        assert_eq!(error.source, PortError(80));
+        assert_eq!(Value::<&u16>::value(&Endpoint::Port(8080)), Some(&8080));
+        assert_eq!(
+            Endpoint::from("h".to_string()),
+            Endpoint::Host("h".to_string())
+        );
+
+        let (kind, payload) = Endpoint::Port(8080).into_parts();
+        assert_eq!(
+            Endpoint::from_parts(kind, payload).ok(),
+            Some(Endpoint::Port(8080))
+        );
+        let rejected = Endpoint::from_parts(EndpointKind::Port, Box::new(80u16)).unwrap_err();
+        assert_eq!(rejected.downcast_ref::<u16>(), Some(&80));
+    }
+
+    #[test]
+    fn test_catch_all() {
+        use std::any::Any;
+
+        #[derive(Debug)]
+        struct Custom(u8);
+
+        fn check_port(port: &u16) -> Result<(), ()> {
+            if *port >= 1024 { Ok(()) } else { Err(()) }
+        }
+
+        #[derive(Debug, TypeEnum)]
+        enum Dynamic {
+            Number(i64),
+            Text(String),
+            Pair(u8, u8),
+            #[type_enum(validate(with = "check_port", error = "()"))]
+            Port(u16),
+            #[type_enum(no_from)]
+            Flag(bool),
+            #[type_enum(catch_all)]
+            Other(Box<dyn Any + Send>),
+        }
+
+        assert!(matches!(Dynamic::from_any(1i64), Dynamic::Number(1)));
+        assert!(matches!(Dynamic::from_any("a".to_string()), Dynamic::Text(text) if text == "a"));
+        assert!(matches!(Dynamic::from_any((1u8, 2u8)), Dynamic::Pair(1, 2)));
+        let other = Dynamic::from_any(Custom(7));
+        assert!(matches!(other, Dynamic::Other(_)));
+        assert_eq!(
+            other.downcast_value::<Custom>().map(|custom| custom.0),
+            Some(7)
+        );
+        assert!(other.downcast_value::<i64>().is_none());
+
+        // Variants without an unchecked `From` are left to the catch-all
+        let port = Dynamic::from_any(5u16);
+        assert_eq!(port.downcast_value::<u16>(), Some(&5));
+        assert!(matches!(Dynamic::from_any(true), Dynamic::Other(_)));
+        assert_eq!(Dynamic::Flag(true).get::<bool>(), Some(&true));
+    }
+
+    #[test]
+    fn test_variant_tags() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Frame {
+            #[type_enum(tag = 7)]
+            Data(Vec<u8>),
+            #[type_enum(tag = 1)]
+            Ping(u64),
+            #[type_enum(tag = 0x100)]
+            Close,
+        }
+
+        assert_eq!(Frame::from(vec![1]).tag(), 7);
+        assert_eq!(Frame::Close.tag(), 256);
+        assert_eq!(FrameKind::Ping.tag(), 1);
+        assert_eq!(FrameKind::from_tag(7), Some(FrameKind::Data));
+        assert_eq!(FrameKind::from_tag(2), None);
+    }
+
+    #[test]
+    fn test_as_ref_targets() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Field<T> {
+            #[type_enum(as_ref(str, std::path::Path))]
+            Name(String),
+            #[type_enum(as_ref([T]))]
+            Items(Vec<T>),
+        }
+
+        let name = Field::<u32>::from("id".to_string());
+        assert_eq!(Value::<&str>::value(&name), Some("id"));
+        assert_eq!(
+            Value::<&std::path::Path>::value(&name),
+            Some(std::path::Path::new("id"))
+        );
+        assert_eq!(Value::<&[u32]>::value(&name), None);
+        let items = Field::from(vec![1u32, 2]);
+        assert_eq!(Value::<&[u32]>::value(&items), Some(&[1, 2][..]));
+    }
+
+    #[test]
+    fn test_flatten() {
+        mod json {
+            use crate::TypeEnum;
+
+            #[derive(Debug, PartialEq, TypeEnum)]
+            pub enum JsonValue {
+                Number(f64),
+                Text(String),
+                #[type_enum(no_from)]
+                Flag(bool),
+            }
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Document {
+            #[type_enum(flatten)]
+            Json(json::JsonValue),
+            Bytes(Vec<u8>),
+        }
+
+        assert_eq!(
+            Document::from(1.5),
+            Document::Json(json::JsonValue::Number(1.5))
+        );
+        assert_eq!(
+            Document::from("a".to_string()),
+            Document::Json(json::JsonValue::Text("a".to_string()))
+        );
+        assert_eq!(
+            Document::from(json::JsonValue::Flag(true)),
+            Document::Json(json::JsonValue::Flag(true))
+        );
+        assert_eq!(Document::from(vec![1u8]), Document::Bytes(vec![1]));
+        assert_eq!(Document::from(1.5).kind(), DocumentKind::Json);
+    }
+
+    #[test]
+    fn test_display() {
+        #[derive(Debug, TypeEnum)]
+        #[type_enum(display)]
+        enum Message<T> {
+            Text(String),
+            Count(Box<T>),
+            #[type_enum(skip)]
+            Note(String),
+            Empty,
+        }
+
+        assert_eq!(Message::<u8>::from("hi".to_string()).to_string(), "hi");
+        assert_eq!(Message::from(Box::new(3u8)).to_string(), "3");
+        assert_eq!(Message::<u8>::Note("n".to_string()).to_string(), "n");
+        assert_eq!(format!("{:>6}", Message::<u8>::Empty), " Empty");
+    }
+
+    #[test]
+    fn test_display_formats() {
+        #[derive(Debug, TypeEnum)]
+        enum Reading {
+            #[type_enum(display = "got a number: {0}")]
+            Number(i64),
+            Text(String),
+            #[type_enum(display = "{}..{} ({0:?} to {1:?}) {{}}")]
+            Range(u8, u8),
+            #[type_enum(display = "span from {start} to {end:>3}")]
+            Span {
+                start: usize,
+                end: usize,
+            },
+            #[type_enum(display = "nothing")]
+            Empty,
+        }
+
+        assert_eq!(Reading::from(5).to_string(), "got a number: 5");
+        assert_eq!(Reading::from("x".to_string()).to_string(), "x");
+        assert_eq!(Reading::from((1u8, 2u8)).to_string(), "1..2 (1 to 2) {}");
+        assert_eq!(
+            Reading::Span { start: 1, end: 4 }.to_string(),
+            "span from 1 to   4"
+        );
+        assert_eq!(Reading::Empty.to_string(), "nothing");
+    }
+
+    #[test]
+    fn test_error_enum() {
+        use std::fmt;
+
+        #[derive(Debug)]
+        struct Inner;
+
+        impl fmt::Display for Inner {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("inner")
+            }
+        }
+
+        impl std::error::Error for Inner {}
+
+        #[derive(Debug)]
+        struct Outer(Inner);
+
+        impl fmt::Display for Outer {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("outer")
+            }
+        }
+
+        impl std::error::Error for Outer {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        #[derive(Debug, TypeEnum)]
+        #[type_enum(error)]
+        enum AppError {
+            Io(std::io::Error),
+            Outer(Outer),
+            Cancelled,
+        }
+
+        fn fail() -> Result<(), Box<dyn std::error::Error>> {
+            Err(AppError::from(Outer(Inner)))?
+        }
+
+        let error = fail().unwrap_err();
+        assert_eq!(error.to_string(), "outer");
+        assert_eq!(error.source().unwrap().to_string(), "inner");
+        let cancelled = AppError::Cancelled;
+        assert_eq!(cancelled.to_string(), "Cancelled");
+        assert!(std::error::Error::source(&cancelled).is_none());
+    }
+
+    #[test]
+    fn test_is_methods() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(is_methods)]
+        enum Token {
+            Number(i64),
+            Ident(String),
+            Eof,
+        }
+
+        let token = Token::from(3);
+        assert!(token.is_number());
+        assert!(!token.is_ident());
+        assert!(Token::Eof.is_eof());
+        assert!(token.holds::<i64>());
+        assert!(!token.holds::<String>());
+    }
+
+    #[test]
+    fn test_snake_case_names() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(getters, is_methods)]
+        enum Endpoint {
+            HTTPServer(String),
+            IOError(u16),
+            Http2Frame(Vec<u8>),
+            r#Socket(bool),
+        }
+
+        let server = Endpoint::from("a".to_string());
+        assert!(server.is_http_server());
+        assert_eq!(server.as_http_server(), Some(&"a".to_string()));
+        assert!(Endpoint::from(5u16).is_io_error());
+        assert!(Endpoint::from(vec![1u8]).is_http2_frame());
+        assert_eq!(Endpoint::from(true).as_socket(), Some(&true));
+    }
+
+    #[test]
+    fn test_getters() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(getters)]
+        enum Token {
+            Number(i64),
+            Ident(String),
+            #[type_enum(skip)]
+            Keyword(String),
+            Span {
+                start: usize,
+                end: usize,
+            },
+            Eof,
+        }
+
+        let token = Token::from(3);
+        assert_eq!(token.as_number(), Some(&3));
+        assert_eq!(token.as_ident(), None);
+        assert_eq!(
+            Token::Keyword("fn".to_string()).as_keyword(),
+            Some(&"fn".to_string())
+        );
+        assert_eq!(Token::Span { start: 1, end: 4 }.as_span(), Some((&1, &4)));
+        assert_eq!(Token::Eof.as_number(), None);
+    }
+
+    #[test]
+    fn test_default_variant() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Retry {
+            Count(u32),
+            #[type_enum(default)]
+            Backoff {
+                base_ms: u64,
+                factor: u8,
+            },
+        }
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Slot<T> {
+            #[type_enum(default)]
+            Empty,
+            Items(Vec<T>),
+        }
+
+        #[derive(Default)]
+        struct Config {
+            retry: Retry,
+            slot: Slot<String>,
+        }
+
+        let config = Config::default();
+        assert_eq!(
+            config.retry,
+            Retry::Backoff {
+                base_ms: 0,
+                factor: 0
+            }
+        );
+        assert_eq!(config.slot, Slot::Empty);
+    }
+
+    #[test]
+    fn test_skip_families() {
+        #[derive(Debug, PartialEq)]
+        struct Sorted(Vec<u32>);
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Column {
+            #[type_enum(skip(value_mut, into_value))]
+            Sorted(Sorted),
+            Raw(Vec<u32>),
+        }
+
+        // Would conflict with a generated impl
+        impl<'a> ValueMut<'a, &'a mut Sorted> for Column {
+            fn value_mut(&'a mut self) -> Option<&'a mut Sorted> {
+                None
+            }
+        }
+
+        let mut column = Column::from(Sorted(vec![1, 2]));
+        assert_eq!(column.kind(), ColumnKind::Sorted);
+        assert_eq!(Value::<&Sorted>::value(&column), Some(&Sorted(vec![1, 2])));
+        assert_eq!(ValueMut::<&mut Sorted>::value_mut(&mut column), None);
+        assert_eq!(<Sorted as Payload<Column>>::KIND, ColumnKind::Sorted);
+        assert_eq!(Column::from(vec![3]).into_value(), Ok(vec![3]));
+    }
+
+    #[test]
+    fn test_no_from_variant() {
+        #[derive(Debug, PartialEq)]
+        struct ParseError(String);
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum AppError {
+            #[type_enum(no_from)]
+            Parse(ParseError),
+            Code(i32),
+        }
+
+        // Written by hand, as thiserror's #[from] would
+        impl From<ParseError> for AppError {
+            fn from(error: ParseError) -> Self {
+                AppError::Parse(ParseError(format!("parse: {}", error.0)))
+            }
+        }
+
+        let error = AppError::from(ParseError("eof".to_string()));
+        assert_eq!(
+            Value::<&ParseError>::value(&error),
+            Some(&ParseError("parse: eof".to_string()))
+        );
+        assert_eq!(error.kind(), AppErrorKind::Parse);
+        assert_eq!(error.into_value(), Ok(ParseError("parse: eof".to_string())));
+    }
+
+    #[test]
+    fn test_unit_variants() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(parts)]
+        enum Slot {
+            Empty,
+            Filled(String),
+            Reserved,
+        }
+
+        assert_eq!(Slot::from("x".to_string()).kind(), SlotKind::Filled);
+        assert_eq!(Slot::Empty.kind(), SlotKind::Empty);
+        assert_eq!(Slot::Reserved.variant_name(), "Reserved");
+        assert_eq!(Value::<&String>::value(&Slot::Empty), None);
+        assert_eq!(<<Slot as TypeEnum>::Types as type_list::TypeList>::LEN, 1);
+
+        let (kind, payload) = Slot::Reserved.into_parts();
+        assert_eq!(Slot::from_parts(kind, payload).unwrap(), Slot::Reserved);
+    }
+
+    #[test]
+    fn test_named_variants() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        #[type_enum(ref_enum, parts)]
+        enum Event {
+            Login { user: String, at: u64 },
+            Ping { id: u32 },
+            Move(i8, i8),
+        }
+
+        let login = Event::from(("ann".to_string(), 10u64));
+        assert_eq!(
+            login,
+            Event::Login {
+                user: "ann".to_string(),
+                at: 10
+            }
+        );
+        assert_eq!(
+            Value::<(&String, &u64)>::value(&login),
+            Some((&"ann".to_string(), &10))
+        );
+        assert_eq!(login.login_user(), Some(&"ann".to_string()));
+        assert_eq!(login.login_at(), Some(&10));
+        assert!(matches!(login.as_ref(), EventRef::Login { at: 10, .. }));
+
+        let mut ping = Event::from(7u32);
+        *ValueMut::<&mut u32>::value_mut(&mut ping).unwrap() += 1;
+        assert_eq!(IntoValue::<u32>::into_value(ping), Ok(8));
+
+        let (kind, payload) = Event::from_login("bob".to_string(), 1).into_parts();
+        assert_eq!(kind, EventKind::Login);
+        assert_eq!(
+            Event::from_parts(kind, payload).unwrap(),
+            Event::from(("bob".to_string(), 1u64))
+        );
+    }
+
+    #[test]
+    fn test_sort_by_type() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, TypeEnum)]
+        enum Entry {
+            Name(String),
+            Id(u32),
+            Flag(bool),
+        }
+
+        let mut values = values![Entry; 3u32, true, "b".to_string(), 1u32, "a".to_string()];
+        sort_by_type(&mut values);
+        assert_eq!(
+            values,
+            values![Entry; "b".to_string(), "a".to_string(), 3u32, 1u32, true]
+        );
+
+        sort_by_type_then_value(&mut values);
+        assert_eq!(
+            values,
+            values![Entry; "a".to_string(), "b".to_string(), 1u32, 3u32, true]
+        );
+
+        sort_by_type_key(&mut values, |kind| match kind {
+            EntryKind::Flag => 0,
+            EntryKind::Id => 1,
+            EntryKind::Name => 2,
+        });
+        assert_eq!(
+            values,
+            values![Entry; true, 1u32, 3u32, "a".to_string(), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_dispatch_table() {
+        use dispatch::DispatchTable;
+
+        #[derive(TypeEnum)]
+        enum Shape {
+            Circle(f64),
+            Label(String),
+            Count(u32),
+            #[type_enum(skip)]
+            Empty(()),
+        }
+
+        #[derive(TypeEnum)]
+        enum Input {
+            Number(i64),
+            Text(String),
+        }
+
+        static SIZE: DispatchTable<Input, usize> = dispatch_table!(Input => usize;
+            String => |s| s.len(),
+            i64 => |n| n.unsigned_abs() as usize,
+        );
+        assert_eq!(SIZE.call(&Input::Number(-4)), 4);
+        assert_eq!(SIZE.call(&Input::from("abc".to_string())), 3);
+
+        static NAME: DispatchTable<Shape, &str> = dispatch_table!(Shape => &'static str;
+            f64 => |_| "circle",
+            String => |label| if label.is_empty() { "blank" } else { "label" },
+            u32 => |_| "count";
+            else |_| "other"
+        );
+        assert_eq!(NAME.call(&Shape::Circle(1.0)), "circle");
+        assert_eq!(NAME.call(&Shape::Label(String::new())), "blank");
+        assert_eq!(NAME.call(&Shape::Count(1)), "count");
+        assert_eq!(NAME.call(&Shape::Empty(())), "other");
+    }
+
+    #[test]
+    fn test_transform() {
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Target {
+            Float(f64),
+            Size(usize),
+        }
+
+        let convert = |value: ExampleDerive| {
+            transform!(ExampleDerive; value;
+                i64 => |n| Target::from(n as f64),
+                String => |s| Target::from(s.len()),
+                (u8, u8) => |(a, b)| Target::from(usize::from(a) + usize::from(b)),
+            )
+        };
+        assert_eq!(convert(2i64.into()), Target::Float(2.0));
+        assert_eq!(convert("abc".to_string().into()), Target::Size(3));
+        assert_eq!(convert((1u8, 2u8).into()), Target::Size(3));
+
+        #[derive(TypeEnum)]
+        enum WithSkipped {
+            Number(i64),
+            #[type_enum(skip)]
+            Other(i64),
+        }
+
+        let convert = |value: WithSkipped| {
+            transform!(WithSkipped; value;
+                i64 => |n| n;
+                else |other| match other {
+                    WithSkipped::Other(n) => -n,
+                    WithSkipped::Number(n) => n,
+                }
+            )
+        };
+        assert_eq!(convert(WithSkipped::Number(1)), 1);
+        assert_eq!(convert(WithSkipped::Other(1)), -1);
+    }
+
+    #[test]
+    fn test_impl_value() {
+        #[derive(Debug, PartialEq)]
+        enum Shape {
+            Circle(f64),
+            Label(String),
+            #[allow(unused)]
+            Rect {
+                width: f64,
+                height: f64,
+            },
+        }
+
+        impl_value!(Shape { Circle => f64, Label => String });
+
+        let mut circle = Shape::Circle(1.0);
+        assert_eq!(circle.value(), Some(&1.0));
+        assert_eq!(Value::<&String>::value(&circle), None);
+        if let Some(radius) = ValueMut::<&mut f64>::value_mut(&mut circle) {
+            *radius = 2.0;
+        }
+        assert_eq!(IntoValue::<f64>::into_value(circle), Ok(2.0));
+        let label = Shape::Label("x".to_string());
+        assert_eq!(
+            IntoValue::<f64>::into_value(label),
+            Err(Shape::Label("x".to_string()))
+        );
+    }
+
+    #[test]
+    #[deny(unused_must_use)]
+    fn test_must_use_opt_out() {
+        #[derive(TypeEnum)]
+        #[type_enum(must_use = false, parts)]
+        enum Quiet {
+            Small(u8),
+            Large(u16),
+        }
+
+        // Without `#[must_use]` these may be called for their side effects alone
+        Quiet::from(1u8).kind();
+        Quiet::from(2u16).into_parts();
+    }
+
+    #[test]
+    fn test_dyn_type_enum() {
+        use dynamic::DynTypeEnum;
+
+        #[derive(Debug, PartialEq, TypeEnum)]
+        enum Other {
+            Flag(bool),
+        }
+
+        let values: Vec<Box<dyn DynTypeEnum>> = vec![
+            Box::new(ExampleDerive::Number(1)),
+            Box::new(Other::Flag(true)),
+        ];
+        let names: Vec<_> = values.iter().map(|value| value.variant_name()).collect();
+        assert_eq!(names, ["Number", "Flag"]);
+        assert!(values[0].type_name().ends_with("ExampleDerive"));
+        assert_eq!(values[1].downcast_ref::<Other>(), Some(&Other::Flag(true)));
+        assert_eq!(values[1].downcast_ref::<ExampleDerive>(), None);
+    }
+
+    #[cfg(feature = "erased-serde")]
+    #[test]
+    fn test_serialize_dyn_type_enum() {
+        use dynamic::SerializeTypeEnum;
+
+        #[derive(TypeEnum)]
+        #[type_enum(serde)]
+        enum Left {
+            Count(u32),
+        }
+
+        #[derive(TypeEnum)]
+        #[type_enum(serde)]
+        enum Right {
+            Name(String),
+        }
+
+        let values: Vec<Box<dyn SerializeTypeEnum>> =
+            vec![Box::new(Left::Count(1)), Box::new(Right::Name("x".into()))];
+        assert_eq!(values[1].variant_name(), "Name");
+        assert_eq!(
+            serde_json::to_string(&values).unwrap(),
+            r#"[{"type":"Count","value":1},{"type":"Name","value":"x"}]"#
+        );
+    }
+
+    #[cfg(feature = "dyn-clone")]
+    #[test]
+    fn test_clone_dyn_type_enum() {
+        use dynamic::{CloneTypeEnum, DynTypeEnum};
+
+        #[derive(Debug, Clone, PartialEq, TypeEnum)]
+        enum Other {
+            Flag(bool),
+        }
+
+        let values: Vec<Box<dyn CloneTypeEnum>> = vec![
+            Box::new(ExampleDerive::Number(1)),
+            Box::new(Other::Flag(true)),
+        ];
+        let copies = values.clone();
+        assert_eq!(copies[0].variant_name(), "Number");
+        let other: &dyn DynTypeEnum = &*copies[1];
+        assert_eq!(other.downcast_ref::<Other>(), Some(&Other::Flag(true)));
+    }
+
+    #[test]
+    fn test_type_enum_decl() {
+        type_enum_decl! {
+            #[derive(Debug, PartialEq)]
+            enum Setting {
+                Retries(u32),
+                /// The display name
+                Name(String),
+            }
+        }
+
+        let setting = Setting::from("x".to_string());
+        assert_eq!(setting, Setting::Name("x".to_string()));
+        assert_eq!(Value::<&String>::value(&setting), Some(&"x".to_string()));
+        assert_eq!(IntoValue::<u32>::into_value(Setting::from(3u32)), Ok(3));
+    }
+
     #[test]
     fn test_skip_attribute() {
         #[allow(unused)]
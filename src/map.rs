@@ -0,0 +1,215 @@
+//! A map holding at most one value per payload type
+
+use std::marker::PhantomData;
+use std::mem;
+
+use crate::{IntoValue, Value, ValueMut};
+
+/// A collection of enum values holding at most one value of each variant
+///
+/// Values are looked up by payload type: `map.get::<String>()` returns the held `String`, if any.
+/// Lookups by type need the single-reference `Value` impls, so multi-field variants can only be
+/// stored with [`insert_value`](TypeEnumMap::insert_value) and read back through iteration.
+/// Enums have few variants, so values are kept in a `Vec` and found by a linear scan.
+pub struct TypeEnumMap<E> {
+    values: Vec<E>,
+}
+
+impl<E> TypeEnumMap<E> {
+    /// Create an empty map
+    pub fn new() -> Self {
+        TypeEnumMap { values: Vec::new() }
+    }
+
+    /// Number of values in the map
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the map holds no values
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over the values, in insertion order
+    pub fn iter(&self) -> std::slice::Iter<'_, E> {
+        self.values.iter()
+    }
+
+    /// Insert an enum value, returning the value of the same variant it replaces
+    pub fn insert_value(&mut self, value: E) -> Option<E> {
+        let position = self
+            .values
+            .iter()
+            .position(|existing| mem::discriminant(existing) == mem::discriminant(&value));
+        match position {
+            Some(index) => Some(mem::replace(&mut self.values[index], value)),
+            None => {
+                self.values.push(value);
+                None
+            }
+        }
+    }
+
+    /// Insert a payload, returning the value of the same variant it replaces
+    pub fn insert<T>(&mut self, value: T) -> Option<E>
+    where
+        E: From<T>,
+    {
+        self.insert_value(value.into())
+    }
+
+    /// Get the held `T`, if any
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        E: for<'a> Value<'a, &'a T>,
+    {
+        self.values.iter().find_map(|value| value.value())
+    }
+
+    /// Get the held `T` mutably, if any
+    pub fn get_mut<T>(&mut self) -> Option<&mut T>
+    where
+        E: for<'a> ValueMut<'a, &'a mut T>,
+    {
+        self.values.iter_mut().find_map(|value| value.value_mut())
+    }
+
+    /// Remove and return the held `T`, if any
+    pub fn remove<T>(&mut self) -> Option<T>
+    where
+        E: for<'a> Value<'a, &'a T> + IntoValue<T>,
+    {
+        let index = self.position::<T>()?;
+        self.values.remove(index).into_value().ok()
+    }
+
+    /// Get the entry for `T`, for in-place insertion or modification with a single lookup
+    pub fn entry<T>(&mut self) -> Entry<'_, E, T>
+    where
+        E: for<'a> Value<'a, &'a T>,
+    {
+        let index = self.position::<T>();
+        Entry {
+            map: self,
+            index,
+            _payload: PhantomData,
+        }
+    }
+
+    fn position<T>(&self) -> Option<usize>
+    where
+        E: for<'a> Value<'a, &'a T>,
+    {
+        self.values
+            .iter()
+            .position(|value| Value::<&T>::value(value).is_some())
+    }
+}
+
+impl<E> Default for TypeEnumMap<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> IntoIterator for TypeEnumMap<E> {
+    type Item = E;
+    type IntoIter = std::vec::IntoIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'m, E> IntoIterator for &'m TypeEnumMap<E> {
+    type Item = &'m E;
+    type IntoIter = std::slice::Iter<'m, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl<E> FromIterator<E> for TypeEnumMap<E> {
+    /// Collect values into a map; later values replace earlier ones of the same variant
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        let mut map = TypeEnumMap::new();
+        for value in iter {
+            map.insert_value(value);
+        }
+        map
+    }
+}
+
+/// The slot for payload type `T` in a [`TypeEnumMap`], returned by [`TypeEnumMap::entry`]
+pub struct Entry<'m, E, T> {
+    map: &'m mut TypeEnumMap<E>,
+    /// Index of the value holding a `T`, if there is one
+    index: Option<usize>,
+    _payload: PhantomData<fn() -> T>,
+}
+
+impl<'m, E, T> Entry<'m, E, T>
+where
+    E: From<T> + for<'a> ValueMut<'a, &'a mut T>,
+{
+    /// Whether the map already holds a `T`
+    pub fn is_occupied(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Get the held `T`, inserting `default` first if there is none
+    pub fn or_insert(self, default: T) -> &'m mut T {
+        self.or_insert_with(|| default)
+    }
+
+    /// Get the held `T`, inserting the result of `default` first if there is none
+    pub fn or_insert_with(self, default: impl FnOnce() -> T) -> &'m mut T {
+        let index = match self.index {
+            Some(index) => index,
+            None => {
+                self.map.values.push(default().into());
+                self.map.values.len() - 1
+            }
+        };
+        self.map.values[index]
+            .value_mut()
+            .expect("entry index points at a value holding its payload type")
+    }
+
+    /// Get the held `T`, inserting `T::default()` first if there is none
+    pub fn or_default(self) -> &'m mut T
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+
+    /// Call `f` with the held `T`, if there is one
+    pub fn and_modify(self, f: impl FnOnce(&mut T)) -> Self {
+        if let Some(index) = self.index
+            && let Some(value) = self.map.values[index].value_mut()
+        {
+            f(value);
+        }
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<E: serde::Serialize> serde::Serialize for TypeEnumMap<E> {
+    /// Serialize as a sequence of the held values
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.values)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, E: serde::Deserialize<'de>> serde::Deserialize<'de> for TypeEnumMap<E> {
+    /// Deserialize from a sequence of values; later values replace earlier ones of the same
+    /// variant
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<E>::deserialize(deserializer).map(|values| values.into_iter().collect())
+    }
+}
@@ -0,0 +1,34 @@
+//! Support for the `From<Enum> for opentelemetry::Value` impls generated by `#[type_enum(otel)]`
+//!
+//! Generated code calls `OtelPayload(payload).into_otel_value()` with both traits below in scope.
+//! Method resolution tries `OtelPayload<T>` before `&OtelPayload<T>`, so payloads convertible
+//! with `Value::from` use that, and other `Display` payloads fall back to their string form.
+
+use std::fmt::Display;
+
+use opentelemetry::Value;
+
+/// A payload being converted into a `Value`
+pub struct OtelPayload<T>(pub T);
+
+/// Payloads that `Value` converts from directly
+pub trait FromPayload {
+    fn into_otel_value(self) -> Value;
+}
+
+impl<T: Into<Value>> FromPayload for OtelPayload<T> {
+    fn into_otel_value(self) -> Value {
+        self.0.into()
+    }
+}
+
+/// Other payloads, recorded as their `Display` output
+pub trait DisplayPayload {
+    fn into_otel_value(self) -> Value;
+}
+
+impl<T: Display> DisplayPayload for &OtelPayload<T> {
+    fn into_otel_value(self) -> Value {
+        Value::String(self.0.to_string().into())
+    }
+}
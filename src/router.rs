@@ -0,0 +1,147 @@
+//! Routing enum requests to one `tower::Service` per payload type
+
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tower::Service;
+
+use crate::IntoValue;
+
+type BoxFuture<R, Er> = Pin<Box<dyn Future<Output = Result<R, Er>> + Send>>;
+
+/// Error returned by a [`Router`]
+#[derive(Debug)]
+pub enum RouterError<E, Er> {
+    /// No route was registered for the payload type held by the request
+    Unrouted(E),
+    /// The service handling the request failed
+    Service(Er),
+}
+
+impl<E, Er: fmt::Display> fmt::Display for RouterError<E, Er> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouterError::Unrouted(_) => write!(f, "no route for the request's payload type"),
+            RouterError::Service(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: fmt::Debug, Er: std::error::Error + 'static> std::error::Error for RouterError<E, Er> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RouterError::Unrouted(_) => None,
+            RouterError::Service(err) => Some(err),
+        }
+    }
+}
+
+/// A service handling one payload type of the enum `E`
+trait Route<E, R, Er>: Send {
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Er>>;
+
+    /// Call the service if `request` holds its payload type, or hand the request back
+    fn try_call(&mut self, request: E) -> Result<BoxFuture<R, Er>, E>;
+}
+
+struct PayloadRoute<T, S> {
+    service: S,
+    _payload: PhantomData<fn(T)>,
+}
+
+impl<E, T, S> Route<E, S::Response, S::Error> for PayloadRoute<T, S>
+where
+    E: IntoValue<T>,
+    S: Service<T> + Send,
+    S::Future: Send + 'static,
+{
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn try_call(&mut self, request: E) -> Result<BoxFuture<S::Response, S::Error>, E> {
+        let payload = request.into_value()?;
+        Ok(Box::pin(self.service.call(payload)))
+    }
+}
+
+/// A `tower::Service` accepting the enum `E` and dispatching each request to the service
+/// registered for the payload type it holds
+///
+/// Every route shares the response type `R` and error type `Er`. The router is ready only once
+/// all of its routes are ready, so backpressure from any route applies to the whole router.
+pub struct Router<E, R, Er> {
+    routes: Vec<Box<dyn Route<E, R, Er>>>,
+}
+
+impl<E, R, Er> Router<E, R, Er> {
+    /// Create a router without any routes
+    pub fn new() -> Self {
+        Router { routes: Vec::new() }
+    }
+
+    /// Send requests holding a `T` to `service`
+    pub fn route<T, S>(mut self, service: S) -> Self
+    where
+        E: IntoValue<T> + 'static,
+        T: 'static,
+        S: Service<T, Response = R, Error = Er> + Send + 'static,
+        S::Future: Send + 'static,
+        R: 'static,
+        Er: 'static,
+    {
+        self.routes.push(Box::new(PayloadRoute {
+            service,
+            _payload: PhantomData,
+        }));
+        self
+    }
+}
+
+impl<E, R, Er> Default for Router<E, R, Er> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, R, Er> Service<E> for Router<E, R, Er>
+where
+    E: Send + 'static,
+    R: Send + 'static,
+    Er: Send + 'static,
+{
+    type Response = R;
+    type Error = RouterError<E, Er>;
+    type Future = Pin<Box<dyn Future<Output = Result<R, RouterError<E, Er>>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut pending = false;
+        for route in &mut self.routes {
+            match route.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(RouterError::Service(err))),
+                Poll::Pending => pending = true,
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn call(&mut self, mut request: E) -> Self::Future {
+        for route in &mut self.routes {
+            match route.try_call(request) {
+                Ok(future) => {
+                    return Box::pin(async move { future.await.map_err(RouterError::Service) });
+                }
+                Err(unmatched) => request = unmatched,
+            }
+        }
+        Box::pin(std::future::ready(Err(RouterError::Unrouted(request))))
+    }
+}
@@ -0,0 +1,80 @@
+//! Per-variant counters generated by `#[type_enum(telemetry)]`
+//!
+//! Every conversion into the enum through `From` counts as a construction of its variant, and
+//! every `value()`, `value_mut()` or `into_value()` call asking for a payload type the enum
+//! doesn't hold counts as a failed extraction of the variant that was asked for. Counting uses
+//! relaxed atomics, so it is cheap enough to leave on in a running service.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::TypeEnum;
+
+/// The counters kept for one variant
+#[derive(Debug)]
+pub struct Counters {
+    constructed: AtomicU64,
+    failed_extractions: AtomicU64,
+}
+
+impl Counters {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        Counters {
+            constructed: AtomicU64::new(0),
+            failed_extractions: AtomicU64::new(0),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn record_construction(&self) {
+        self.constructed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[doc(hidden)]
+    pub fn record_failed_extraction(&self) {
+        self.failed_extractions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Read the current counts
+    pub fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            constructed: self.constructed.load(Ordering::Relaxed),
+            failed_extractions: self.failed_extractions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Set both counts back to zero
+    pub fn reset(&self) {
+        self.constructed.store(0, Ordering::Relaxed);
+        self.failed_extractions.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The counts of one variant at the time of a [`Counters::snapshot`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CounterSnapshot {
+    /// Values of the variant created through `From`
+    pub constructed: u64,
+    /// Extractions of the variant's payload type from a value holding another variant
+    pub failed_extractions: u64,
+}
+
+/// Implemented by enums deriving `TypeEnum` with `#[type_enum(telemetry)]`
+pub trait Telemetry: TypeEnum {
+    /// The counters of every variant, in the order of [`TypeEnum::KINDS`]
+    fn counters() -> &'static [Counters];
+
+    /// The current counts of every variant, next to its kind
+    fn snapshot() -> Vec<(Self::Kind, CounterSnapshot)> {
+        Self::KINDS
+            .iter()
+            .zip(Self::counters())
+            .map(|(kind, counters)| (*kind, counters.snapshot()))
+            .collect()
+    }
+
+    /// Set the counts of every variant back to zero
+    fn reset() {
+        Self::counters().iter().for_each(Counters::reset);
+    }
+}
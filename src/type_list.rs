@@ -0,0 +1,93 @@
+//! Type-level lists of payload types
+//!
+//! [`TypeEnum::Types`](crate::TypeEnum::Types) lists the payload types of a derived enum as
+//! `Cons<A, Cons<B, Nil>>`, which generic code can inspect without macros.
+
+use std::marker::PhantomData;
+
+/// The empty type list
+pub struct Nil;
+
+/// A type list with `H` as its first element, followed by the list `T`
+pub struct Cons<H, T>(PhantomData<(H, T)>);
+
+/// Implemented by every type list
+pub trait TypeList {
+    /// Number of types in the list
+    const LEN: usize;
+}
+
+impl TypeList for Nil {
+    const LEN: usize = 0;
+}
+
+impl<H, T: TypeList> TypeList for Cons<H, T> {
+    const LEN: usize = T::LEN + 1;
+}
+
+/// Index of a type found at the head of a list
+pub struct Here;
+
+/// Index of a type found in the tail of a list, at index `I` of the tail
+pub struct There<I>(PhantomData<I>);
+
+/// Implemented by lists containing `T`
+///
+/// `I` is the position of `T` in the list and is always inferred by the compiler, so bounds are
+/// written as `L: Member<T, I>` with `I` as an extra generic parameter.
+pub trait Member<T, I>: TypeList {
+    /// Position of `T` in the list
+    const INDEX: usize;
+}
+
+impl<T, Tail: TypeList> Member<T, Here> for Cons<T, Tail> {
+    const INDEX: usize = 0;
+}
+
+impl<T, H, Tail: Member<T, I>, I> Member<T, There<I>> for Cons<H, Tail> {
+    const INDEX: usize = Tail::INDEX + 1;
+}
+
+/// Implemented by lists with an element at the index `I`, written with [`Here`] and [`There`]
+pub trait At<I>: TypeList {
+    /// The element at index `I`
+    type Output;
+}
+
+impl<H, T: TypeList> At<Here> for Cons<H, T> {
+    type Output = H;
+}
+
+impl<H, T: At<I>, I> At<There<I>> for Cons<H, T> {
+    type Output = T::Output;
+}
+
+/// Implemented by lists that can have the list `Other` appended
+pub trait Concat<Other: TypeList>: TypeList {
+    /// The elements of this list followed by the elements of `Other`
+    type Output: TypeList;
+}
+
+impl<Other: TypeList> Concat<Other> for Nil {
+    type Output = Other;
+}
+
+impl<H, T: Concat<Other>, Other: TypeList> Concat<Other> for Cons<H, T> {
+    type Output = Cons<H, T::Output>;
+}
+
+/// Implemented by lists whose elements each appear in `Full` exactly once
+///
+/// Like `I` in [`Member`], `Indices` lists the positions of the elements and is inferred. It is
+/// ambiguous when an element appears more than once, so `L: Distinct<L, _>` fails to compile for
+/// lists with duplicates.
+pub trait Distinct<Full, Indices>: TypeList {}
+
+impl<Full> Distinct<Full, Nil> for Nil {}
+
+impl<H, T, Full, I, Is> Distinct<Full, Cons<I, Is>> for Cons<H, T>
+where
+    T: Distinct<Full, Is>,
+    Full: Member<H, I>,
+{
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "derive")]
+
+use type_enum::{IntoValue, TypeEnum, Value};
+
+#[derive(Debug, PartialEq, TypeEnum)]
+enum Setting {
+    Retries(u32),
+    Name(String),
+}
+
+#[test]
+fn derive_outside_the_crate() {
+    let setting = Setting::from(3);
+    assert_eq!(setting.kind(), SettingKind::Retries);
+    assert_eq!(setting.value(), Some(&3u32));
+    assert_eq!(
+        Setting::from("x".to_string()).into_value(),
+        Ok("x".to_string())
+    );
+}
+
+mod reexport {
+    pub use type_enum as traits;
+}
+
+#[derive(Debug, PartialEq, TypeEnum)]
+#[type_enum(crate = "reexport::traits")]
+enum Renamed {
+    Count(u8),
+}
+
+#[test]
+fn derive_through_a_reexport() {
+    assert_eq!(Renamed::from(1).variant_name(), "Count");
+}
+
+mod json {
+    use type_enum::TypeEnum;
+
+    #[derive(Debug, PartialEq, TypeEnum)]
+    pub enum JsonValue {
+        Number(f64),
+        Text(String),
+        #[type_enum(no_from)]
+        Flag(bool),
+    }
+}
+
+#[derive(Debug, PartialEq, TypeEnum)]
+enum Document {
+    #[type_enum(flatten)]
+    Json(json::JsonValue),
+    Bytes(Vec<u8>),
+}
+
+#[test]
+fn flatten_outside_the_crate() {
+    assert_eq!(
+        Document::from(1.5),
+        Document::Json(json::JsonValue::Number(1.5))
+    );
+    assert_eq!(
+        Document::from("a".to_string()),
+        Document::Json(json::JsonValue::Text("a".to_string()))
+    );
+    assert_eq!(Document::from(vec![1u8]), Document::Bytes(vec![1]));
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "derive")]
+
 #[test]
 fn ui_tests() {
     let t = trybuild::TestCases::new();
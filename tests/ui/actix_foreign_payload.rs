@@ -0,0 +1,12 @@
+use type_enum::TypeEnum;
+
+struct Ping;
+
+#[derive(TypeEnum)]
+#[type_enum(actix)]
+enum Command {
+    Ping(Ping),
+    Rename(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Message {
+    #[type_enum(boxed)]
+    Big(Vec<u8>),
+    Small(u8),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Buf<const N: usize> {
+    Fixed([u8; N]),
+    Small([u8; 4]),
+}
+
+fn main() {}
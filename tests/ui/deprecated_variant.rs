@@ -0,0 +1,14 @@
+#![deny(deprecated)]
+
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Message {
+    Ping(u8),
+    #[deprecated = "use `Ping`"]
+    Legacy(u16),
+}
+
+fn main() {
+    let _ = MessageKind::Legacy;
+}
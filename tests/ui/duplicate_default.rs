@@ -0,0 +1,11 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Setting {
+    #[type_enum(default)]
+    Retries(u32),
+    #[type_enum(default)]
+    Name(String),
+}
+
+fn main() {}
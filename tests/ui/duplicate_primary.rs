@@ -0,0 +1,11 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Output {
+    #[type_enum(primary)]
+    Stdout(String),
+    #[type_enum(primary)]
+    Stderr(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Field {
+    JsonText(String),
+    #[type_enum(rename = "json_text")]
+    Json(Vec<u8>),
+}
+
+fn main() {}
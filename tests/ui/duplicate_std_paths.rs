@@ -0,0 +1,16 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Message {
+    Text(String),
+    Count(u8),
+    Name(std::string::String),
+}
+
+#[derive(TypeEnum)]
+enum Nested {
+    Bytes(Vec< u8 >),
+    Raw(::alloc::vec::Vec<core::primitive::u8>),
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Frame {
+    #[type_enum(tag = 1)]
+    Data(Vec<u8>),
+    #[type_enum(tag = 1)]
+    Ping(u64),
+}
+
+fn main() {}
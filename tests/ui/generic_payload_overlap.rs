@@ -0,0 +1,9 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Wrapper<T> {
+    Item(T),
+    Label(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(ref_enum)]
+enum Wrapper<T> {
+    Items(Vec<T>),
+    Label(String),
+}
+
+fn main() {}
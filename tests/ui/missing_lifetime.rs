@@ -0,0 +1,9 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Borrowed {
+    Name(&str),
+    Count(usize),
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(TypeEnum)]
+enum Never {}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(only(from, value), test_roundtrip)]
+enum Setting {
+    Retries(u32),
+    Name(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(auto_box_over = 16)]
+enum Message {
+    Ping(u8),
+    Snapshot([u64; 8]),
+}
+
+fn main() {}
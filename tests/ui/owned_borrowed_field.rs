@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(owned)]
+enum Token<'a> {
+    Word(&'a str),
+    Words(Vec<&'a str>),
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use type_enum::TypeEnum;
+
+type Id = u64;
+
+#[derive(TypeEnum)]
+enum Lookup {
+    Count(u64),
+    #[type_enum(same_as = "u64")]
+    User(Id),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Setting {
+    #[type_enum(skip(value_ref))]
+    Retries(u32),
+    Name(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Arg {
+    #[type_enum(from(&str, i64))]
+    Text(String),
+    Number(i64),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Borrowed {
+    Names(Vec<&'a str>),
+    Count(usize),
+}
+
+fn main() {}
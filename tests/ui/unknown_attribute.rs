@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum UnknownAttribute {
+    Foo(String),
+    #[type_enum(skipp)]
+    Bar(usize),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(serde(rename_all = "camel_case"))]
+enum Setting {
+    MaxRetries(u32),
+    DisplayName(String),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(transitions(Idle -> Runing))]
+enum Job {
+    Idle(u8),
+    Running(u16),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+#[type_enum(delegate_ops(Add, Neg))]
+enum UnsupportedOperator {
+    Int(i64),
+    Float(f64),
+}
+
+fn main() {}
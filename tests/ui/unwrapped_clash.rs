@@ -0,0 +1,30 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use type_enum::TypeEnum;
+
+#[derive(TypeEnum)]
+enum Shared {
+    #[type_enum(shared)]
+    A(Arc<String>),
+    #[type_enum(shared)]
+    B(Rc<String>),
+}
+
+#[derive(TypeEnum)]
+enum Mixed {
+    #[type_enum(boxed)]
+    Boxed(Box<Vec<u8>>),
+    #[type_enum(shared)]
+    Shared(Arc<Vec<u8>>),
+}
+
+#[derive(TypeEnum)]
+enum Text<'a> {
+    Borrowed(Cow<'a, str>),
+    #[type_enum(shared)]
+    Shared(Rc<String>),
+}
+
+fn main() {}
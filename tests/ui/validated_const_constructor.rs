@@ -0,0 +1,22 @@
+use type_enum::TypeEnum;
+
+struct PortError;
+
+fn check_port(port: &u16) -> Result<(), PortError> {
+    if *port >= 1024 { Ok(()) } else { Err(PortError) }
+}
+
+#[derive(TypeEnum)]
+#[type_enum(const_constructors)]
+enum Endpoint {
+    #[type_enum(validate(with = "check_port", error = "PortError"))]
+    Port(u16),
+    #[type_enum(no_from)]
+    Retries(u32),
+    Host(String),
+}
+
+const PORT: Endpoint = Endpoint::from_u16(80);
+const RETRIES: Endpoint = Endpoint::from_u32(3);
+
+fn main() {}
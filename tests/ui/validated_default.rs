@@ -0,0 +1,16 @@
+use type_enum::TypeEnum;
+
+struct PortError;
+
+fn check_port(port: &u16) -> Result<(), PortError> {
+    if *port >= 1024 { Ok(()) } else { Err(PortError) }
+}
+
+#[derive(TypeEnum)]
+enum Endpoint {
+    #[type_enum(default, validate(with = "check_port", error = "PortError"))]
+    Port(u16),
+    Host(String),
+}
+
+fn main() {}
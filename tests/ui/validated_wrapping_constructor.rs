@@ -0,0 +1,22 @@
+use type_enum::TypeEnum;
+
+struct Config {
+    port: u16,
+}
+
+struct ConfigError;
+
+fn check_config(config: &Box<Config>) -> Result<(), ConfigError> {
+    if config.port >= 1024 { Ok(()) } else { Err(ConfigError) }
+}
+
+#[derive(TypeEnum)]
+enum Setting {
+    #[type_enum(validate(with = "check_config", error = "ConfigError"))]
+    Config(Box<Config>),
+    Name(String),
+}
+
+fn main() {
+    let _ = Setting::new_config(Config { port: 80 });
+}